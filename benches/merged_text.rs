@@ -0,0 +1,126 @@
+//! Compares spawning a glyph entity per glyph against merging the glyphs of a text into one mesh.
+//!
+//! Run with `cargo bench --bench merged_text`.
+
+use std::time::{Duration, Instant};
+
+use bevy::{input::InputPlugin, prelude::*};
+use bevy_text3d::prelude::*;
+
+const FONT: &str = "assets/fonts/Fira_Sans-Regular.ttf";
+const LENGTHS: [usize; 3] = [200, 2_000, 10_000];
+const UPDATES: u32 = 20;
+
+fn main() {
+    println!(
+        "{:>8} {:>10} {:>9} {:>14} {:>14}",
+        "glyphs", "mode", "entities", "first layout", "re-layout"
+    );
+    for length in LENGTHS {
+        for merged in [false, true] {
+            let result = bench(length, merged);
+            println!(
+                "{:>8} {:>10} {:>9} {:>14.2?} {:>14.2?}",
+                length,
+                if merged { "merged" } else { "per glyph" },
+                result.entities,
+                result.first_layout,
+                result.relayout,
+            );
+        }
+    }
+}
+
+struct BenchResult {
+    /// The entities drawn for the text.
+    entities: usize,
+    /// Laying out the text with the glyph meshes already cached.
+    first_layout: Duration,
+    /// Laying out the text again after changing it, on average.
+    relayout: Duration,
+}
+
+fn bench(length: usize, merged: bool) -> BenchResult {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        Text3dPlugin::<StandardMaterial>::default(),
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .init_asset::<Font>();
+
+    let font = Font::try_from_bytes(
+        std::fs::read(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(FONT)).unwrap(),
+    )
+    .unwrap();
+    let font = app.world.resource_mut::<Assets<Font>>().add(font);
+    let text = Text::from_section(
+        paragraph(length),
+        TextStyle {
+            font,
+            font_size: 40.0,
+            color: Color::WHITE,
+        },
+    );
+    let spawn = |app: &mut App| {
+        let mut entity = app.world.spawn((
+            Text3dBundle {
+                text: text.clone().into(),
+                ..default()
+            },
+            Text3dBounds::new(Vec2::new(2_000.0, f32::INFINITY)),
+        ));
+        if merged {
+            entity.insert(Text3dMerged);
+        }
+        entity.id()
+    };
+
+    // tessellate the glyphs once, so only layout and spawning are measured
+    let warm_up = spawn(&mut app);
+    app.update();
+    app.world.entity_mut(warm_up).despawn_recursive();
+
+    let entity = spawn(&mut app);
+    let start = Instant::now();
+    app.update();
+    let first_layout = start.elapsed();
+    let entities = app
+        .world
+        .get::<Children>(entity)
+        .map_or(0, |children| children.len());
+
+    let start = Instant::now();
+    for update in 0..UPDATES {
+        let mut text_3d = app.world.get_mut::<Text3d>(entity).unwrap();
+        text_3d.sections[0].value = paragraph(length + update as usize % 2);
+        app.update();
+    }
+    let relayout = start.elapsed() / UPDATES;
+
+    BenchResult {
+        entities,
+        first_layout,
+        relayout,
+    }
+}
+
+/// Text of roughly `length` glyphs, wrapped into lines by the bounds.
+fn paragraph(length: usize) -> String {
+    const WORDS: &str = "the quick brown fox jumps over the lazy dog while sphinx of black \
+        quartz judge my vow and pack my box with five dozen liquor jugs";
+    WORDS
+        .split(' ')
+        .cycle()
+        .scan(0, |glyphs, word| {
+            *glyphs += word.len();
+            (*glyphs <= length).then_some(word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
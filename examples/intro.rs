@@ -10,7 +10,7 @@ fn main() {
             DefaultPlugins,
             NoCameraPlayerPlugin,
             WireframePlugin,
-            Text3dPlugin,
+            Text3dPlugin::<StandardMaterial>::default(),
         ))
         .insert_resource(Msaa::Sample8)
         .insert_resource(ClearColor(Color::rgb(0.52734375, 0.8046875, 0.91796875)))
@@ -69,45 +69,56 @@ fn setup(
         ..default()
     });
 
-    // Text at origin
-    commands.spawn(Text3dBundle {
-        text: Text::from_sections([
-            TextSection::new(
-                "Use WASD to move and the mouse to look around.\n",
-                TextStyle {
-                    font: asset_server.load("fonts/Dancing_Script-Medium.ttf"),
-                    font_size: 40.0,
-                    color: Color::rgb(1.0, 0.9, 0.5),
-                },
-            ),
-            TextSection::new(
-                "Press Space to rise, and Shift to fall. Now, fall.\n",
-                TextStyle {
-                    font: asset_server.load("fonts/Airstrip_Four-Regular.ttf"),
-                    font_size: 40.0,
-                    color: Color::rgb(0.5, 0.9, 1.0),
-                },
-            ),
-            TextSection::new(
-                "Hi there. Press F to toggle wireframes. Come closer.\n",
-                TextStyle {
-                    font: asset_server.load("fonts/Open_Sans-Italic.ttf"),
-                    font_size: 40.0,
-                    color: Color::rgb(0.8, 0.0, 0.7),
-                },
-            ),
-            TextSection::new(
-                "Press T to turn on the lights. Then, rise.\n",
-                TextStyle {
-                    font: asset_server.load("fonts/Fira_Mono-Bold.ttf"),
-                    font_size: 40.0,
-                    color: Color::rgb(0.8, 0.9, 0.7),
-                },
-            ),
+    // Text at origin, typed out a character at a time
+    commands.spawn((
+        Text3dBundle {
+            text: Text::from_sections([
+                TextSection::new(
+                    "Use WASD to move and the mouse to look around.\n",
+                    TextStyle {
+                        font: asset_server.load("fonts/Dancing_Script-Medium.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(1.0, 0.9, 0.5),
+                    },
+                ),
+                TextSection::new(
+                    "Press Space to rise, and Shift to fall. Now, fall.\n",
+                    TextStyle {
+                        font: asset_server.load("fonts/Airstrip_Four-Regular.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.5, 0.9, 1.0),
+                    },
+                ),
+                TextSection::new(
+                    "Hi there. Press F to toggle wireframes. Come closer.\n",
+                    TextStyle {
+                        font: asset_server.load("fonts/Open_Sans-Italic.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.8, 0.0, 0.7),
+                    },
+                ),
+                TextSection::new(
+                    "Press T to turn on the lights. Then, rise.\n",
+                    TextStyle {
+                        font: asset_server.load("fonts/Fira_Mono-Bold.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.8, 0.9, 0.7),
+                    },
+                ),
+            ])
+            .into(),
+            ..default()
+        },
+        Text3dAnimation::new([
+            Text3dEffect::Typewriter,
+            Text3dEffect::FadeIn { duration: 0.5 },
+            Text3dEffect::Wave {
+                amplitude: 2.0,
+                frequency: 0.5,
+            },
         ])
-        .into(),
-        ..default()
-    });
+        .with_stagger(0.03),
+    ));
 }
 
 // fn zoom_and_pan(
@@ -1,4 +1,4 @@
-use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+use std::f32::consts::FRAC_PI_2;
 
 use bevy::prelude::*;
 use bevy_flycam::prelude::*;
@@ -6,7 +6,11 @@ use bevy_text3d::prelude::*;
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, NoCameraPlayerPlugin, Text3dPlugin))
+        .add_plugins((
+            DefaultPlugins,
+            NoCameraPlayerPlugin,
+            Text3dPlugin::<StandardMaterial>::default(),
+        ))
         .insert_resource(Msaa::Sample8)
         .add_systems(Startup, setup)
         .add_systems(Update, rotate_things)
@@ -62,13 +66,16 @@ fn setup(
         });
 }
 
+// not yet added to the app
+#[allow(dead_code)]
 fn slide_text(time: Res<Time>, mut query: Query<(&Name, &mut Transform)>) {
-    let dt = time.delta_seconds();
+    let _dt = time.delta_seconds();
     for (_, mut transform) in query.iter_mut() {
         transform.translation.y += 0.1;
     }
 }
 
+#[allow(dead_code)]
 #[derive(Component)]
 struct Sliding;
 
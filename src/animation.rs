@@ -0,0 +1,203 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+/// Animates the glyphs of a [`Text3d`](crate::pipeline::Text3d), one character after another.
+///
+/// Each character starts its effects `stagger` seconds after the one before it, counting every
+/// character of every section, including whitespace. Effects are applied on top of where the glyphs
+/// were laid out, as recorded by their [`Text3dGlyph`](crate::pipeline::Text3dGlyph), so text
+/// merged with [`Text3dMerged`](crate::pipeline::Text3dMerged) isn't animated. Colour effects only
+/// apply to glyphs drawn in the colour of their section, which are given a material of their own
+/// until their colour settles back on that of their section.
+///
+/// Removing the component puts the glyphs back as they were laid out.
+#[derive(Component, Clone, Debug)]
+pub struct Text3dAnimation {
+    /// The effects, applied in order.
+    pub effects: Vec<Text3dEffect>,
+    /// How fast time passes for the animation, where 1 is real time.
+    pub speed: f32,
+    /// The seconds between one character starting its effects and the next.
+    pub stagger: f32,
+    /// The seconds the animation has been playing for. Set it to 0 to play it again.
+    pub elapsed: f32,
+}
+
+impl Default for Text3dAnimation {
+    fn default() -> Self {
+        Self {
+            effects: Vec::new(),
+            speed: 1.0,
+            stagger: 0.05,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Text3dAnimation {
+    pub fn new(effects: impl IntoIterator<Item = Text3dEffect>) -> Self {
+        Self {
+            effects: effects.into_iter().collect(),
+            ..default()
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_stagger(mut self, stagger: f32) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    /// Whether the animation has revealed every character, with `count` characters in the text.
+    ///
+    /// Looping effects such as [`Text3dEffect::Wave`] never finish, so this only covers those that
+    /// play once.
+    pub fn is_revealed(&self, count: usize) -> bool {
+        let last = count.saturating_sub(1) as f32 * self.stagger;
+        self.effects
+            .iter()
+            .all(|effect| self.elapsed >= last + effect.duration())
+    }
+}
+
+/// An effect of a [`Text3dAnimation`].
+///
+/// Distances are in the units of the text, in which a glyph is as tall as its font size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Text3dEffect {
+    /// Hides each character until its turn comes.
+    Typewriter,
+    /// Fades each character in from transparent over `duration` seconds.
+    FadeIn { duration: f32 },
+    /// Grows each character from nothing over `duration` seconds, overshooting its size before
+    /// settling.
+    ScalePop { duration: f32 },
+    /// Bobs the characters up and down `frequency` times a second, one after another.
+    Wave { amplitude: f32, frequency: f32 },
+    /// Jitters each character `frequency` times a second, by up to `amplitude` in each direction.
+    Shake { amplitude: f32, frequency: f32 },
+    /// Cycles the colour of the characters through the hues `frequency` times a second, one after
+    /// another, keeping their alpha.
+    Rainbow {
+        frequency: f32,
+        saturation: f32,
+        lightness: f32,
+    },
+}
+
+impl Text3dEffect {
+    pub const FADE_IN: Self = Self::FadeIn { duration: 0.3 };
+    pub const SCALE_POP: Self = Self::ScalePop { duration: 0.3 };
+    pub const WAVE: Self = Self::Wave {
+        amplitude: 4.0,
+        frequency: 1.0,
+    };
+    pub const SHAKE: Self = Self::Shake {
+        amplitude: 1.0,
+        frequency: 20.0,
+    };
+    pub const RAINBOW: Self = Self::Rainbow {
+        frequency: 0.5,
+        saturation: 0.8,
+        lightness: 0.6,
+    };
+
+    /// How long the effect takes to play out for a character, or 0 if it loops.
+    fn duration(&self) -> f32 {
+        match *self {
+            Self::FadeIn { duration } | Self::ScalePop { duration } => duration,
+            _ => 0.0,
+        }
+    }
+}
+
+/// How a character looks at a moment of its animation.
+pub(crate) struct GlyphPose {
+    pub(crate) visible: bool,
+    pub(crate) offset: Vec3,
+    pub(crate) scale: f32,
+    pub(crate) color: Option<Color>,
+}
+
+impl Text3dAnimation {
+    /// How the character at `index` in the text looks now, with the colour it was laid out in.
+    pub(crate) fn pose(&self, index: usize, color: Color) -> GlyphPose {
+        let time = self.elapsed;
+        // the time since the character started its effects
+        let local = time - index as f32 * self.stagger;
+        let mut pose = GlyphPose {
+            visible: true,
+            offset: Vec3::ZERO,
+            scale: 1.0,
+            color: None,
+        };
+        for effect in self.effects.iter() {
+            match *effect {
+                Text3dEffect::Typewriter => pose.visible &= local >= 0.0,
+                Text3dEffect::FadeIn { duration } => {
+                    let alpha = progress(local, duration);
+                    let color = pose.color.unwrap_or(color);
+                    pose.color = Some(color.with_a(color.a() * alpha));
+                }
+                Text3dEffect::ScalePop { duration } => {
+                    pose.scale *= ease_out_back(progress(local, duration));
+                }
+                Text3dEffect::Wave {
+                    amplitude,
+                    frequency,
+                } => pose.offset.y += amplitude * (TAU * frequency * local).sin(),
+                Text3dEffect::Shake {
+                    amplitude,
+                    frequency,
+                } => {
+                    let step = (time * frequency).floor() as u32;
+                    let x = noise(index as u32, step, 0);
+                    let y = noise(index as u32, step, 1);
+                    pose.offset += Vec3::new(x, y, 0.0) * amplitude;
+                }
+                Text3dEffect::Rainbow {
+                    frequency,
+                    saturation,
+                    lightness,
+                } => {
+                    let hue = (frequency * local).rem_euclid(1.0) * 360.0;
+                    let alpha = pose.color.unwrap_or(color).a();
+                    pose.color = Some(Color::hsla(hue, saturation, lightness, alpha));
+                }
+            }
+        }
+        pose
+    }
+}
+
+/// How far through `duration` seconds `time` is, from 0 to 1.
+fn progress(time: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        return if time >= 0.0 { 1.0 } else { 0.0 };
+    }
+    (time / duration).clamp(0.0, 1.0)
+}
+
+/// Eases from 0 to 1, overshooting 1 before settling on it.
+fn ease_out_back(t: f32) -> f32 {
+    const C1: f32 = 1.70158;
+    const C3: f32 = C1 + 1.0;
+    1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+}
+
+/// A number from -1 to 1 that looks random, but is always the same for the same inputs.
+fn noise(index: u32, step: u32, axis: u32) -> f32 {
+    let mut hash = index
+        .wrapping_mul(0x9E37_79B9)
+        .wrapping_add(step.wrapping_mul(0x85EB_CA6B))
+        .wrapping_add(axis.wrapping_mul(0xC2B2_AE35));
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
@@ -0,0 +1,330 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bevy::{prelude::*, text::BreakLineOn};
+use cosmic_text::{
+    fontdb, Align, Attrs, Buffer, Family, FontFeatures, FontSystem, Metrics, Shaping, Wrap,
+};
+use glyph_brush_layout::ab_glyph::{Font as _, FontArc, GlyphId, PxScale};
+use glyph_brush_layout::{FontId, SectionGlyph};
+
+use crate::pipeline::{Text3dAlignment, NOTDEF};
+
+/// Shapes and lays out text with cosmic-text, which handles ligatures, complex scripts and
+/// bidirectional text.
+///
+/// Characters missing from the font of their section are set in the first of the
+/// [`Text3dFontFallback`](crate::pipeline::Text3dFontFallback) fonts that has them. Fonts are told
+/// apart by their family name, weight, style and stretch, so two fonts alike in all of those can't
+/// both be used.
+#[derive(Resource)]
+pub(crate) struct TextLayouter {
+    font_system: FontSystem,
+    faces: HashMap<AssetId<Font>, Face>,
+    /// The font of each face, by its id in cosmic-text. The handles are weak, so fonts can still be
+    /// unloaded.
+    fonts: HashMap<fontdb::ID, (Handle<Font>, FontArc)>,
+    /// The fonts characters missing from the font of their section are set in, in order.
+    pub(crate) fallbacks: Vec<Handle<Font>>,
+}
+
+impl Default for TextLayouter {
+    fn default() -> Self {
+        // the locale only guides the choice of fallback fonts
+        let font_system =
+            FontSystem::new_with_locale_and_db("en-US".to_string(), fontdb::Database::new());
+        Self {
+            font_system,
+            faces: HashMap::new(),
+            fonts: HashMap::new(),
+            fallbacks: Vec::new(),
+        }
+    }
+}
+
+/// A font as cosmic-text knows it.
+struct Face {
+    family: String,
+    weight: fontdb::Weight,
+    style: fontdb::Style,
+    stretch: fontdb::Stretch,
+}
+
+/// Laid out glyphs, with the fonts they are set in.
+#[derive(Default)]
+pub(crate) struct LaidOutText {
+    /// The glyphs, with their `font_id` indexing `fonts` and `handles`.
+    pub(crate) glyphs: Vec<SectionGlyph>,
+    pub(crate) fonts: Vec<FontArc>,
+    pub(crate) handles: Vec<Handle<Font>>,
+}
+
+impl TextLayouter {
+    /// Lays out the sections of the text at `scale` times their font size, wrapping lines wider
+    /// than `width`.
+    ///
+    /// Returns `None` if any font of the text, or any fallback font, has not loaded yet.
+    pub(crate) fn layout(
+        &mut self,
+        text: &Text,
+        features: &[FontFeatures],
+        alignment: Text3dAlignment,
+        fonts: &Assets<Font>,
+        width: f32,
+        scale: f32,
+    ) -> Option<LaidOutText> {
+        let fallbacks = self.fallbacks.clone();
+        for handle in text
+            .sections
+            .iter()
+            .map(|section| &section.style.font)
+            .chain(fallbacks.iter())
+        {
+            let font = fonts.get(handle)?;
+            self.load(handle, font);
+        }
+
+        // each section is split into runs of characters set in the same font, being the font of
+        // the section or else the first fallback font with a glyph for the character
+        let mut spans: Vec<(&str, Attrs)> = Vec::new();
+        for (idx, section) in text.sections.iter().enumerate() {
+            let section_font = &fonts.get(&section.style.font)?.font;
+            let font_size = (section.style.font_size * scale).max(f32::MIN_POSITIVE);
+            let line_height = font_size
+                * (section_font.height_unscaled() + section_font.line_gap_unscaled())
+                / section_font.height_unscaled();
+            let font_of = |c: char| {
+                if c.is_whitespace() || section_font.glyph_id(c) != NOTDEF {
+                    return &section.style.font;
+                }
+                fallbacks
+                    .iter()
+                    .find(|handle| fonts.get(*handle).unwrap().font.glyph_id(c) != NOTDEF)
+                    .unwrap_or(&section.style.font)
+            };
+            let mut chars = section.value.char_indices().peekable();
+            while let Some((start, c)) = chars.next() {
+                let handle = font_of(c);
+                let mut end = start + c.len_utf8();
+                while let Some((next, c)) = chars.next_if(|(_, c)| font_of(*c) == handle) {
+                    end = next + c.len_utf8();
+                }
+                // font sizes are the height of the font, as in glyph_brush_layout, but
+                // cosmic-text sizes fonts by their em square
+                let em = font_size / height_per_em(&fonts.get(handle).unwrap().font);
+                let attrs = Attrs::new()
+                    .metadata(idx)
+                    .metrics(Metrics::new(em, line_height))
+                    .font_features(features.get(idx).cloned().unwrap_or_default());
+                // fonts cosmic-text couldn't read fall back to any other font
+                let attrs = match self.faces.get(&handle.id()) {
+                    Some(face) => attrs
+                        .family(Family::Name(&face.family))
+                        .weight(face.weight)
+                        .style(face.style)
+                        .stretch(face.stretch),
+                    None => attrs,
+                };
+                spans.push((&section.value[start..end], attrs));
+            }
+        }
+        let Some(default_attrs) = spans.first().map(|(_, attrs)| attrs.clone()) else {
+            return Some(LaidOutText::default());
+        };
+
+        let font_system = &mut self.font_system;
+        let metrics = default_attrs
+            .metrics_opt
+            .map_or(Metrics::new(1.0, 1.0), Metrics::from);
+        let mut buffer = Buffer::new(font_system, metrics);
+        let wrap = match text.linebreak_behavior {
+            BreakLineOn::WordBoundary => Wrap::Word,
+            BreakLineOn::AnyCharacter => Wrap::Glyph,
+            BreakLineOn::NoWrap => Wrap::None,
+        };
+        buffer.set_wrap(font_system, wrap);
+        let width = (wrap != Wrap::None && width.is_finite()).then_some(width);
+        buffer.set_size(font_system, width, None);
+        // left aligned paragraphs start from the right if they are written right to left
+        let align = match alignment {
+            Text3dAlignment::Left => None,
+            Text3dAlignment::Center => Some(Align::Center),
+            Text3dAlignment::Right => Some(Align::Right),
+            Text3dAlignment::Justified => Some(Align::Justified),
+        };
+        buffer.set_rich_text(font_system, spans, &default_attrs, Shaping::Advanced, align);
+        // without a width, paragraphs are only aligned within themselves, so align them within
+        // the widest line instead
+        if width.is_none() {
+            let widest = buffer
+                .layout_runs()
+                .map(|run| run.line_w)
+                .fold(0.0, f32::max);
+            buffer.set_size(font_system, Some(widest), None);
+        }
+
+        // where each line of the buffer starts in the text, and where each section starts
+        let string = text
+            .sections
+            .iter()
+            .map(|section| section.value.as_str())
+            .collect::<String>();
+        let mut line_starts = Vec::with_capacity(buffer.lines.len());
+        let mut start = 0;
+        for line in buffer.lines.iter() {
+            line_starts.push(start);
+            start += line.text().len();
+            // skip the line ending
+            let rest = &string[start.min(string.len())..];
+            start += if rest.starts_with("\r\n") {
+                2
+            } else {
+                rest.chars().next().map_or(0, char::len_utf8)
+            };
+        }
+        let section_starts = text
+            .sections
+            .iter()
+            .scan(0, |start, section| {
+                let section_start = *start;
+                *start += section.value.len();
+                Some(section_start)
+            })
+            .collect::<Vec<_>>();
+
+        let mut laid_out = LaidOutText::default();
+        let mut font_ids = HashMap::new();
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs.iter() {
+                let Some((handle, font)) = self.fonts.get(&glyph.font_id) else {
+                    continue;
+                };
+                let font_id = *font_ids.entry(glyph.font_id).or_insert_with(|| {
+                    laid_out.fonts.push(font.clone());
+                    laid_out.handles.push(handle.clone());
+                    laid_out.fonts.len() - 1
+                });
+                let x = glyph.x + glyph.font_size * glyph.x_offset;
+                let y = run.line_y + glyph.y - glyph.font_size * glyph.y_offset;
+                let section_index = glyph.metadata;
+                let byte_index =
+                    line_starts[run.line_i] + glyph.start - section_starts[section_index];
+                laid_out.glyphs.push(SectionGlyph {
+                    section_index,
+                    byte_index,
+                    glyph: GlyphId(glyph.glyph_id).with_scale_and_position(
+                        PxScale::from(glyph.font_size * height_per_em(font)),
+                        (x, y),
+                    ),
+                    font_id: FontId(font_id),
+                });
+            }
+        }
+        Some(laid_out)
+    }
+
+    /// Makes the font known to cosmic-text.
+    fn load(&mut self, handle: &Handle<Font>, font: &Font) {
+        if self.faces.contains_key(&handle.id()) {
+            return;
+        }
+        let db = self.font_system.db_mut();
+        let source = fontdb::Source::Binary(Arc::new(font.font.font_data().to_vec()));
+        let mut ids = db.load_font_source(source).into_iter();
+        // only the first font of a collection is used, as with glyph_brush_layout
+        let first = ids.next();
+        for id in ids {
+            db.remove_face(id);
+        }
+        let Some(id) = first else {
+            return;
+        };
+        let Some((family, _)) = db.face(id).and_then(|info| info.families.first()) else {
+            db.remove_face(id);
+            return;
+        };
+        let info = db.face(id).unwrap();
+        self.faces.insert(
+            handle.id(),
+            Face {
+                family: family.clone(),
+                weight: info.weight,
+                style: info.style,
+                stretch: info.stretch,
+            },
+        );
+        self.fonts
+            .insert(id, (handle.clone_weak(), font.font.clone()));
+    }
+
+    /// Makes cosmic-text forget the font.
+    pub(crate) fn remove_font(&mut self, font: AssetId<Font>) {
+        if self.faces.remove(&font).is_none() {
+            return;
+        }
+        let db = self.font_system.db_mut();
+        self.fonts.retain(|id, (handle, _)| {
+            let keep = handle.id() != font;
+            if !keep {
+                db.remove_face(*id);
+            }
+            keep
+        });
+    }
+}
+
+/// The height of the font in its own units, per unit of its em square.
+fn height_per_em(font: &FontArc) -> f32 {
+    font.height_unscaled() / font.units_per_em().unwrap_or(font.height_unscaled())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out a paragraph that wraps, returning where the first line ends.
+    fn first_line_end(alignment: Text3dAlignment, width: f32) -> f32 {
+        let mut fonts = Assets::<Font>::default();
+        let font =
+            Font::try_from_bytes(include_bytes!("../assets/fonts/Fira_Sans-Regular.ttf").to_vec())
+                .unwrap();
+        let text = Text::from_section(
+            "The quick brown fox jumps over the lazy dog, again and again.",
+            TextStyle {
+                font: fonts.add(font),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+        );
+        let mut layouter = TextLayouter::default();
+        let laid_out = layouter
+            .layout(&text, &[], alignment, &fonts, width, 1.0)
+            .unwrap();
+        let first_baseline = laid_out.glyphs[0].glyph.position.y;
+        assert!(laid_out
+            .glyphs
+            .iter()
+            .any(|glyph| glyph.glyph.position.y != first_baseline));
+        laid_out
+            .glyphs
+            .iter()
+            .filter(|glyph| glyph.glyph.position.y == first_baseline)
+            .filter(|glyph| {
+                let value = &text.sections[glyph.section_index].value;
+                !value[glyph.byte_index..].starts_with(char::is_whitespace)
+            })
+            .map(|glyph| {
+                let font = &laid_out.fonts[glyph.font_id.0];
+                let scale = glyph.glyph.scale.y / font.height_unscaled();
+                glyph.glyph.position.x + font.h_advance_unscaled(glyph.glyph.id) * scale
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn justified_lines_fill_the_width() {
+        let width = 200.0;
+        assert!(first_line_end(Text3dAlignment::Left, width) < width - 1.0);
+        let end = first_line_end(Text3dAlignment::Justified, width);
+        assert!((end - width).abs() < 0.5, "{end}");
+    }
+}
@@ -1,7 +1,22 @@
+pub mod animation;
+mod layout;
+pub mod markup;
 pub mod mesh;
 pub mod pipeline;
 pub mod plugin;
 
 pub mod prelude {
-    pub use crate::plugin::{Text3dBundle, Text3dPlugin};
+    pub use crate::{
+        animation::{Text3dAnimation, Text3dEffect},
+        markup::{MarkupError, Text3dMarkup, Text3dStyle, Text3dStyles},
+        mesh::{BackFace, Bevel, BevelProfile, GlyphMeshSettings, LineCap, LineJoin, Stroke},
+        pipeline::{
+            FeatureTag, FontFeatures, GlyphTessellationFailed, Text3d, Text3dAlignment,
+            Text3dAnchor, Text3dBounds, Text3dCacheBudget, Text3dFontFallback, Text3dFontFeatures,
+            Text3dGenerated, Text3dGlyph, Text3dLine, Text3dLod, Text3dMaterials, Text3dMerged,
+            Text3dOutline, Text3dOverflow, Text3dPrewarm, Text3dPrewarmProgress, Text3dReady,
+            Text3dSize, Text3dTessellation,
+        },
+        plugin::{Text3dBundle, Text3dPlugin},
+    };
 }
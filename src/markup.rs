@@ -0,0 +1,389 @@
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::*;
+
+/// Text written in markup, which sets the sections of the [`Text3d`](crate::pipeline::Text3d) on
+/// the same entity whenever it or the [`Text3dStyles`] change.
+///
+/// See [`Text3dStyles::parse`] for the markup.
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+pub struct Text3dMarkup(pub String);
+
+impl From<&str> for Text3dMarkup {
+    fn from(markup: &str) -> Self {
+        Self(markup.to_string())
+    }
+}
+
+impl From<String> for Text3dMarkup {
+    fn from(markup: String) -> Self {
+        Self(markup)
+    }
+}
+
+/// Changes to a [`TextStyle`], applied to the text inside a markup tag.
+#[derive(Clone, Debug, Default)]
+pub struct Text3dStyle {
+    pub font: Option<Handle<Font>>,
+    pub font_size: Option<f32>,
+    pub color: Option<Color>,
+}
+
+impl Text3dStyle {
+    pub fn with_font(mut self, font: Handle<Font>) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    fn apply(&self, style: &mut TextStyle) {
+        if let Some(font) = &self.font {
+            style.font = font.clone();
+        }
+        if let Some(font_size) = self.font_size {
+            style.font_size = font_size;
+        }
+        if let Some(color) = self.color {
+            style.color = color;
+        }
+    }
+}
+
+/// The styles markup is parsed with: the style of text outside any tag, the named styles tags can
+/// apply, and the named fonts the `font` tag can pick from.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Text3dStyles {
+    /// The style of text outside any tag.
+    pub base: TextStyle,
+    /// The styles applied by tags of their name, such as `[b]` or `[title]`.
+    pub presets: HashMap<String, Text3dStyle>,
+    /// The fonts the `font` tag can pick, by name.
+    pub fonts: HashMap<String, Handle<Font>>,
+}
+
+impl Text3dStyles {
+    pub fn new(base: TextStyle) -> Self {
+        Self { base, ..default() }
+    }
+
+    pub fn with_preset(mut self, name: impl Into<String>, style: Text3dStyle) -> Self {
+        self.presets.insert(name.into(), style);
+        self
+    }
+
+    pub fn with_font(mut self, name: impl Into<String>, font: Handle<Font>) -> Self {
+        self.fonts.insert(name.into(), font);
+        self
+    }
+
+    /// Parses markup into text sections.
+    ///
+    /// Tags are written in square brackets and closed with a slash, as in
+    /// `[color=red]red[/color]`, and can nest. `[/]` closes the innermost tag. The tags are:
+    ///
+    /// - `[color=...]`, with a colour name such as `red`, or a hex colour such as `#ff8000`
+    /// - `[size=...]`, with a font size
+    /// - `[font=...]`, with the name of one of the [`fonts`](Self::fonts)
+    /// - the name of any of the [`presets`](Self::presets), such as `[b]` for a preset named `b`
+    ///
+    /// `[[` is a literal `[`.
+    pub fn parse(&self, markup: &str) -> Result<Vec<TextSection>, MarkupError> {
+        let mut sections = Vec::new();
+        // the open tags, with the style inside each
+        let mut open: Vec<(&str, TextStyle)> = Vec::new();
+        let mut value = String::new();
+        let mut rest = markup;
+        while let Some(start) = rest.find('[') {
+            value.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if let Some(after) = rest.strip_prefix('[') {
+                value.push('[');
+                rest = after;
+                continue;
+            }
+            let end = rest.find(']').ok_or(MarkupError::UnterminatedTag)?;
+            let tag = rest[..end].trim();
+            rest = &rest[end + 1..];
+
+            let style = open.last().map_or(&self.base, |(_, style)| style);
+            if !value.is_empty() {
+                sections.push(TextSection::new(std::mem::take(&mut value), style.clone()));
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                match open.pop() {
+                    Some((open_name, _)) if name.is_empty() || name == open_name => {}
+                    _ => return Err(MarkupError::UnexpectedClosingTag(name.to_string())),
+                }
+                continue;
+            }
+            let (name, tag_value) = match tag.split_once('=') {
+                Some((name, tag_value)) => (name.trim(), Some(tag_value.trim())),
+                None => (tag, None),
+            };
+            let mut style = style.clone();
+            self.apply(name, tag_value, &mut style)?;
+            open.push((name, style));
+        }
+        value.push_str(rest);
+
+        if let Some((name, _)) = open.last() {
+            return Err(MarkupError::UnclosedTag(name.to_string()));
+        }
+        if !value.is_empty() {
+            sections.push(TextSection::new(value, self.base.clone()));
+        }
+        Ok(sections)
+    }
+
+    /// Applies the tag to the style of the text inside it.
+    fn apply(
+        &self,
+        name: &str,
+        value: Option<&str>,
+        style: &mut TextStyle,
+    ) -> Result<(), MarkupError> {
+        let invalid = |value: &str| MarkupError::InvalidValue {
+            tag: name.to_string(),
+            value: value.to_string(),
+        };
+        match (name, value) {
+            ("color", Some(value)) => {
+                style.color = parse_color(value).ok_or_else(|| invalid(value))?;
+            }
+            ("size", Some(value)) => {
+                style.font_size = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|size| size.is_finite() && *size > 0.0)
+                    .ok_or_else(|| invalid(value))?;
+            }
+            ("font", Some(value)) => {
+                style.font = self
+                    .fonts
+                    .get(value)
+                    .cloned()
+                    .ok_or_else(|| invalid(value))?;
+            }
+            (_, None) => match self.presets.get(name) {
+                Some(preset) => preset.apply(style),
+                None => return Err(MarkupError::UnknownTag(name.to_string())),
+            },
+            (_, Some(_)) => return Err(MarkupError::UnknownTag(name.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// A colour by name or in hex.
+fn parse_color(value: &str) -> Option<Color> {
+    let color = match value.to_ascii_lowercase().as_str() {
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "gray" | "grey" => Color::GRAY,
+        "silver" => Color::SILVER,
+        "red" => Color::RED,
+        "maroon" => Color::MAROON,
+        "orange" => Color::ORANGE,
+        "yellow" => Color::YELLOW,
+        "olive" => Color::OLIVE,
+        "lime" => Color::LIME_GREEN,
+        "green" => Color::GREEN,
+        "cyan" | "aqua" => Color::CYAN,
+        "teal" => Color::TEAL,
+        "blue" => Color::BLUE,
+        "navy" => Color::NAVY,
+        "purple" => Color::PURPLE,
+        "magenta" | "fuchsia" => Color::FUCHSIA,
+        "pink" => Color::PINK,
+        "transparent" => Color::NONE,
+        _ => return Color::hex(value.strip_prefix('#')?).ok(),
+    };
+    Some(color)
+}
+
+/// Why markup could not be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkupError {
+    /// A `[` without a `]` to end the tag.
+    UnterminatedTag,
+    /// A tag that is neither built in nor a preset.
+    UnknownTag(String),
+    /// A tag with a value it can't take.
+    InvalidValue { tag: String, value: String },
+    /// A closing tag that doesn't close the innermost open tag.
+    UnexpectedClosingTag(String),
+    /// A tag that is never closed.
+    UnclosedTag(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnterminatedTag => write!(f, "a tag is missing its closing bracket"),
+            Self::UnknownTag(tag) => write!(f, "unknown tag [{tag}]"),
+            Self::InvalidValue { tag, value } => write!(f, "invalid value {value:?} for [{tag}]"),
+            Self::UnexpectedClosingTag(tag) => write!(f, "[/{tag}] doesn't close the open tag"),
+            Self::UnclosedTag(tag) => write!(f, "[{tag}] is never closed"),
+        }
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles() -> Text3dStyles {
+        Text3dStyles::new(TextStyle {
+            font_size: 10.0,
+            color: Color::WHITE,
+            ..default()
+        })
+        .with_preset("b", Text3dStyle::default().with_font_size(20.0))
+        .with_preset(
+            "warn",
+            Text3dStyle::default()
+                .with_font_size(30.0)
+                .with_color(Color::ORANGE),
+        )
+        .with_font("mono", Handle::weak_from_u128(1))
+    }
+
+    /// The sections as their value, font size and colour.
+    fn parse(markup: &str) -> Vec<(String, f32, Color)> {
+        styles()
+            .parse(markup)
+            .unwrap()
+            .into_iter()
+            .map(|section| (section.value, section.style.font_size, section.style.color))
+            .collect()
+    }
+
+    #[test]
+    fn nested_tags() {
+        assert_eq!(
+            parse("a[color=red]b[size=5]c[/size]d[/color]e"),
+            [
+                ("a".to_string(), 10.0, Color::WHITE),
+                ("b".to_string(), 10.0, Color::RED),
+                ("c".to_string(), 5.0, Color::RED),
+                ("d".to_string(), 10.0, Color::RED),
+                ("e".to_string(), 10.0, Color::WHITE),
+            ]
+        );
+        // `[/]` closes the innermost tag
+        assert_eq!(
+            parse("[b][color=red]a[/]b[/]c"),
+            [
+                ("a".to_string(), 20.0, Color::RED),
+                ("b".to_string(), 20.0, Color::WHITE),
+                ("c".to_string(), 10.0, Color::WHITE),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_brackets() {
+        assert_eq!(
+            parse("[[b] [b][[[/b]"),
+            [
+                ("[b] ".to_string(), 10.0, Color::WHITE),
+                ("[".to_string(), 20.0, Color::WHITE),
+            ]
+        );
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse("[color=Navy]a[/]")[0].2, Color::NAVY);
+        assert_eq!(parse("[color=grey]a[/]")[0].2, Color::GRAY);
+        assert_eq!(
+            parse("[color=#ff8000]a[/]")[0].2,
+            Color::rgb_u8(255, 128, 0)
+        );
+        assert_eq!(
+            parse("[color=#ff800080]a[/]")[0].2,
+            Color::rgba_u8(255, 128, 0, 128)
+        );
+    }
+
+    #[test]
+    fn fonts() {
+        let sections = styles().parse("[font=mono]a[/font]b").unwrap();
+        assert_eq!(sections[0].style.font, Handle::weak_from_u128(1));
+        assert_eq!(sections[1].style.font, Handle::default());
+    }
+
+    #[test]
+    fn presets_apply_over_the_tags_outside_them() {
+        assert_eq!(
+            parse("[color=red][b]a[/b][warn]b[b]c[/b][/warn][/color]"),
+            [
+                // `b` only sets the size, keeping the colour from outside it
+                ("a".to_string(), 20.0, Color::RED),
+                ("b".to_string(), 30.0, Color::ORANGE),
+                ("c".to_string(), 20.0, Color::ORANGE),
+            ]
+        );
+        // tags inside a preset override it
+        assert_eq!(
+            parse("[warn][color=blue][size=1]a[/][/][/]"),
+            [("a".to_string(), 1.0, Color::BLUE)]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let styles = styles();
+        assert_eq!(
+            styles.parse("a[b").unwrap_err(),
+            MarkupError::UnterminatedTag
+        );
+        assert_eq!(
+            styles.parse("[i]a[/i]").unwrap_err(),
+            MarkupError::UnknownTag("i".to_string())
+        );
+        assert_eq!(
+            styles.parse("[b=1]a[/b]").unwrap_err(),
+            MarkupError::UnknownTag("b".to_string())
+        );
+        for (markup, tag, value) in [
+            ("[color=reddish]a[/]", "color", "reddish"),
+            ("[color=#ff80f]a[/]", "color", "#ff80f"),
+            ("[size=-2]a[/]", "size", "-2"),
+            ("[size=big]a[/]", "size", "big"),
+            ("[font=serif]a[/]", "font", "serif"),
+        ] {
+            assert_eq!(
+                styles.parse(markup).unwrap_err(),
+                MarkupError::InvalidValue {
+                    tag: tag.to_string(),
+                    value: value.to_string(),
+                }
+            );
+        }
+        assert_eq!(
+            styles.parse("[b]a[/warn]").unwrap_err(),
+            MarkupError::UnexpectedClosingTag("warn".to_string())
+        );
+        assert_eq!(
+            styles.parse("a[/]").unwrap_err(),
+            MarkupError::UnexpectedClosingTag(String::new())
+        );
+        assert_eq!(
+            styles.parse("[b][warn]a[/warn]").unwrap_err(),
+            MarkupError::UnclosedTag("b".to_string())
+        );
+    }
+}
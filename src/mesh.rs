@@ -1,20 +1,353 @@
+use std::collections::{HashMap, HashSet};
+
 use glyph_brush_layout::ab_glyph::{self, Font, GlyphId, OutlineCurve};
 
 use bevy::{
     prelude::*,
-    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
 };
 
+pub use lyon::tessellation::{FillRule, LineCap, LineJoin};
+
 use lyon::{geom::euclid::Point2D, path::Path, tessellation::*};
 
+/// Edges between side wall segments meeting at a shallower angle than this are smoothed.
+const SMOOTHING_ANGLE: f32 = std::f32::consts::FRAC_PI_6;
+
+/// How far outlines sit in front of the front face, as a fraction of the font's height, so they don't
+/// fight with it for depth.
+const OUTLINE_OFFSET: f32 = 0.002;
+
+/// Corners sharper than this are not mitered when a contour is inset, to keep the inset bounded.
+const MIN_MITER_COS: f32 = -0.9;
+
+/// Settings that control the geometry generated for each glyph of a [`Text3d`](crate::pipeline::Text3d).
+///
+/// Lengths are expressed as a fraction of the font's height, so they scale with the font size.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GlyphMeshSettings {
+    /// How far the curves of the glyph may stray from the generated mesh. Smaller values produce
+    /// smoother glyphs with more triangles.
+    pub tolerance: f32,
+    /// Which areas enclosed by the contours of a glyph are filled.
+    ///
+    /// Both TrueType and CFF outlines use the non-zero rule, which fills overlapping contours
+    /// correctly. Fonts converted from formats that rely on even-odd filling may need `EvenOdd`.
+    pub fill_rule: FillRule,
+    /// How far the glyph is extruded behind its front face. `0.0` produces a flat glyph.
+    pub depth: f32,
+    /// The bevel applied to the edges of extruded glyphs.
+    pub bevel: Bevel,
+    /// What flat glyphs look like from behind. Extruded glyphs always have a back face.
+    pub back_face: BackFace,
+}
+
+impl Default for GlyphMeshSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: Self::DEFAULT_TOLERANCE,
+            fill_rule: FillRule::NonZero,
+            depth: 0.0,
+            bevel: Bevel::default(),
+            back_face: BackFace::default(),
+        }
+    }
+}
+
+impl GlyphMeshSettings {
+    /// About lyon's default tolerance, for a typical font with 1000 units per em.
+    pub const DEFAULT_TOLERANCE: f32 = 0.0001;
+
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_bevel(mut self, bevel: Bevel) -> Self {
+        self.bevel = bevel;
+        self
+    }
+
+    pub fn with_back_face(mut self, back_face: BackFace) -> Self {
+        self.back_face = back_face;
+        self
+    }
+}
+
+/// The back face of flat glyphs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BackFace {
+    /// The glyphs are invisible from behind.
+    #[default]
+    None,
+    /// The glyphs are mirrored from behind, as if seen through the front face.
+    Mirrored,
+    /// The text reads normally from behind, with the glyphs and their order mirrored.
+    ///
+    /// This spawns a second glyph entity, facing backwards, for each glyph.
+    Readable,
+}
+
+/// Rounds off or cuts the edges between the faces and the side walls of extruded glyphs.
+///
+/// The faces are inset by the bevel width, so bevels wider than the strokes of the glyph will
+/// produce overlapping geometry.
+#[derive(Clone, Copy, Debug)]
+pub struct Bevel {
+    /// The width and depth of the bevel, at most half of the glyph depth. `0.0` produces hard edges.
+    pub width: f32,
+    /// How many bands of quads make up each bevel.
+    pub segments: u32,
+    pub profile: BevelProfile,
+}
+
+impl Default for Bevel {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            segments: 4,
+            profile: BevelProfile::Round,
+        }
+    }
+}
+
+impl Bevel {
+    pub fn new(width: f32, segments: u32, profile: BevelProfile) -> Self {
+        Self {
+            width,
+            segments,
+            profile,
+        }
+    }
+
+    /// The bands of quads from the front face, around the side wall, to the back face.
+    fn wall_segments(&self, width: f32, depth: f32) -> Vec<WallSegment> {
+        let side_wall = |front_z: f32, back_z: f32| WallSegment {
+            front: Ring {
+                inset: 0.0,
+                z: front_z,
+                normal: Vec2::X,
+            },
+            back: Ring {
+                inset: 0.0,
+                z: back_z,
+                normal: Vec2::X,
+            },
+        };
+        if width <= 0.0 {
+            return vec![side_wall(0.0, -depth)];
+        }
+
+        let segments = self.segments.max(1);
+        let front_rings = (0..=segments)
+            .map(|i| self.profile.ring(i as f32 / segments as f32, width))
+            .collect::<Vec<_>>();
+        let back_rings = front_rings
+            .iter()
+            .rev()
+            .map(|ring| Ring {
+                inset: ring.inset,
+                z: -depth - ring.z,
+                normal: Vec2::new(ring.normal.x, -ring.normal.y),
+            })
+            .collect::<Vec<_>>();
+
+        let bands = |rings: &[Ring]| {
+            rings
+                .windows(2)
+                .map(|pair| WallSegment {
+                    front: pair[0],
+                    back: pair[1],
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut wall_segments = bands(&front_rings);
+        if depth > 2.0 * width {
+            wall_segments.push(side_wall(-width, width - depth));
+        }
+        wall_segments.extend(bands(&back_rings));
+        wall_segments
+    }
+}
+
+impl PartialEq for Bevel {
+    fn eq(&self, other: &Self) -> bool {
+        self.width.to_bits() == other.width.to_bits()
+            && self.segments == other.segments
+            && self.profile == other.profile
+    }
+}
+
+impl Eq for Bevel {}
+
+impl std::hash::Hash for Bevel {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.width.to_bits().hash(state);
+        self.segments.hash(state);
+        self.profile.hash(state);
+    }
+}
+
+/// The cross-section of a [`Bevel`].
+#[derive(Clone, Copy, Debug)]
+pub enum BevelProfile {
+    /// A straight cut at 45°.
+    Chamfer,
+    /// A quarter circle.
+    Round,
+    /// Maps the progress along the bevel, from `0.0` at the face to `1.0` at the side wall, to how far
+    /// the bevel has moved outwards (`x`) and backwards (`y`), both from `0.0` to `1.0`.
+    Custom(fn(f32) -> Vec2),
+}
+
+impl BevelProfile {
+    fn point(&self, t: f32) -> Vec2 {
+        match self {
+            BevelProfile::Chamfer => Vec2::splat(t),
+            BevelProfile::Round => {
+                let angle = t * std::f32::consts::FRAC_PI_2;
+                Vec2::new(angle.sin(), 1.0 - angle.cos())
+            }
+            BevelProfile::Custom(profile) => profile(t),
+        }
+    }
+
+    fn ring(&self, t: f32, width: f32) -> Ring {
+        let point = self.point(t);
+        let tangent = self.point((t + 1e-3).min(1.0)) - self.point((t - 1e-3).max(0.0));
+        Ring {
+            inset: width * (1.0 - point.x),
+            z: -width * point.y,
+            normal: Vec2::new(tangent.y, tangent.x).normalize_or_zero(),
+        }
+    }
+}
+
+// custom profiles are told apart by the address of their function
+impl PartialEq for BevelProfile {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (BevelProfile::Custom(a), BevelProfile::Custom(b)) => *a as usize == *b as usize,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for BevelProfile {}
+
+impl std::hash::Hash for BevelProfile {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        if let BevelProfile::Custom(profile) = self {
+            (*profile as usize).hash(state);
+        }
+    }
+}
+
+// settings are used as part of the glyph mesh cache key
+impl PartialEq for GlyphMeshSettings {
+    fn eq(&self, other: &Self) -> bool {
+        self.tolerance.to_bits() == other.tolerance.to_bits()
+            && self.fill_rule == other.fill_rule
+            && self.depth.to_bits() == other.depth.to_bits()
+            && self.bevel == other.bevel
+            && self.back_face == other.back_face
+    }
+}
+
+impl Eq for GlyphMeshSettings {}
+
+impl std::hash::Hash for GlyphMeshSettings {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.tolerance.to_bits().hash(state);
+        (self.fill_rule as u8).hash(state);
+        self.depth.to_bits().hash(state);
+        self.bevel.hash(state);
+        self.back_face.hash(state);
+    }
+}
+
+/// How the outline of a glyph is traced.
+///
+/// The width is expressed as a fraction of the font's height, and is centred on the outline.
+#[derive(Clone, Copy, Debug)]
+pub struct Stroke {
+    pub width: f32,
+    pub line_join: LineJoin,
+    pub line_cap: LineCap,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            width: 0.02,
+            line_join: LineJoin::Miter,
+            line_cap: LineCap::Butt,
+        }
+    }
+}
+
+impl Stroke {
+    pub fn new(width: f32) -> Self {
+        Self { width, ..default() }
+    }
+
+    pub fn with_line_join(mut self, line_join: LineJoin) -> Self {
+        self.line_join = line_join;
+        self
+    }
+
+    pub fn with_line_cap(mut self, line_cap: LineCap) -> Self {
+        self.line_cap = line_cap;
+        self
+    }
+}
+
+impl PartialEq for Stroke {
+    fn eq(&self, other: &Self) -> bool {
+        self.width.to_bits() == other.width.to_bits()
+            && self.line_join == other.line_join
+            && self.line_cap == other.line_cap
+    }
+}
+
+impl Eq for Stroke {}
+
+impl std::hash::Hash for Stroke {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.width.to_bits().hash(state);
+        (self.line_join as u8).hash(state);
+        (self.line_cap as u8).hash(state);
+    }
+}
+
 #[derive(Debug)]
 pub enum MeshError {
     NoOutline,
     TessellationError(TessellationError),
 }
 
-// pub fn build_mesh(font: impl Font, the_char: char) -> Option<GlyphMesh> {
-pub fn build_mesh(font: &impl Font, glyph_id: GlyphId) -> Result<GlyphMesh, MeshError> {
+/// The outline of a glyph, in font units.
+struct GlyphPath {
+    path: Path,
+    filler: VertexFiller,
+    font_scale: f32,
+}
+
+fn build_path(font: &impl Font, glyph_id: GlyphId) -> Result<GlyphPath, MeshError> {
     let outline = font.outline(glyph_id).ok_or(MeshError::NoOutline)?;
     let font_scale = font.height_unscaled();
     let mut builder = Path::builder();
@@ -28,93 +361,498 @@ pub fn build_mesh(font: &impl Font, glyph_id: GlyphId) -> Result<GlyphMesh, Mesh
     let width = max_x - min_x;
     let height = max_y - min_y;
 
-    // start path
-    let mut iterator = outline
-        .curves
-        .into_iter()
-        .map(|curve| match curve {
-            OutlineCurve::Line(from, to) => {
-                (from.to_lyon_point(), to.to_lyon_point(), CurveMapping::Line)
+    // the start of the current contour, and where its last curve ended
+    let mut contour: Option<(lyon::math::Point, lyon::math::Point)> = None;
+    let curves = outline.curves.into_iter().map(|curve| match curve {
+        OutlineCurve::Line(from, to) => {
+            (from.to_lyon_point(), to.to_lyon_point(), CurveMapping::Line)
+        }
+        OutlineCurve::Quad(from, ctrl, to) => (
+            from.to_lyon_point(),
+            to.to_lyon_point(),
+            CurveMapping::Quad(ctrl.to_lyon_point()),
+        ),
+        OutlineCurve::Cubic(from, ctrl1, ctrl2, to) => (
+            from.to_lyon_point(),
+            to.to_lyon_point(),
+            CurveMapping::Cubic(ctrl1.to_lyon_point(), ctrl2.to_lyon_point()),
+        ),
+    });
+    for (from, to, curve_type) in curves {
+        // ab_glyph closes every contour with a line back to its start, which may be empty
+        if matches!(curve_type, CurveMapping::Line) && from == to {
+            continue;
+        }
+        // start a contour, closing the last one if it did not make it back to its start
+        let start = match contour {
+            Some((start, last)) if last == from => start,
+            Some(_) => {
+                builder.close();
+                builder.begin(from);
+                from
             }
-            OutlineCurve::Quad(from, ctrl, to) => (
-                from.to_lyon_point(),
-                to.to_lyon_point(),
-                CurveMapping::Quad(ctrl.to_lyon_point()),
-            ),
-            OutlineCurve::Cubic(from, ctrl1, ctrl2, to) => (
-                from.to_lyon_point(),
-                to.to_lyon_point(),
-                CurveMapping::Cubic(ctrl1.to_lyon_point(), ctrl2.to_lyon_point()),
-            ),
-        })
-        .enumerate()
-        .peekable();
-    while let Some((idx, (from, to, curve_type))) = iterator.next() {
-        // if first path, start path
-        if idx == 0 {
-            builder.begin(from);
-        }
-        // take path
+            None => {
+                builder.begin(from);
+                from
+            }
+        };
         match curve_type {
             CurveMapping::Line => builder.line_to(to),
             CurveMapping::Quad(ctrl) => builder.quadratic_bezier_to(ctrl, to),
             CurveMapping::Cubic(ctrl1, ctrl2) => builder.cubic_bezier_to(ctrl1, ctrl2, to),
         };
-        // if required (next is different), finish path, start next path
-        if let Some((_, (next_from, _, _))) = iterator.peek() {
-            if *next_from != to {
-                builder.end(false);
-                builder.begin(*next_from);
-            }
-        } else {
+        // back at the start, so this contour is complete
+        if to == start {
             builder.close();
+            contour = None;
+        } else {
+            contour = Some((start, to));
+        }
+    }
+    if contour.is_some() {
+        builder.close();
+    }
+
+    Ok(GlyphPath {
+        path: builder.build(),
+        filler: VertexFiller {
+            min_x,
+            min_y,
+            width,
+            height,
+        },
+        font_scale,
+    })
+}
+
+/// A rectangle covering the bounds of the glyph.
+fn build_bounds_path(font: &impl Font, glyph_id: GlyphId) -> Result<GlyphPath, MeshError> {
+    let GlyphPath {
+        filler, font_scale, ..
+    } = build_path(font, glyph_id)?;
+    let mut builder = Path::builder();
+    builder.add_rectangle(
+        &lyon::math::Box2D::new(
+            lyon::math::point(filler.min_x, filler.min_y),
+            lyon::math::point(filler.min_x + filler.width, filler.min_y + filler.height),
+        ),
+        lyon::path::Winding::Positive,
+    );
+    Ok(GlyphPath {
+        path: builder.build(),
+        filler,
+        font_scale,
+    })
+}
+
+// pub fn build_mesh(font: impl Font, the_char: char) -> Option<GlyphMesh> {
+pub fn build_mesh(
+    font: &impl Font,
+    glyph_id: GlyphId,
+    settings: &GlyphMeshSettings,
+) -> Result<GlyphMesh, MeshError> {
+    fill_mesh(build_path(font, glyph_id)?, settings)
+}
+
+/// Builds a flat mesh tracing the outline of the glyph, just in front of its front face.
+pub fn build_outline_mesh(
+    font: &impl Font,
+    glyph_id: GlyphId,
+    settings: &GlyphMeshSettings,
+    stroke: &Stroke,
+) -> Result<GlyphMesh, MeshError> {
+    outline_mesh(build_path(font, glyph_id)?, settings, stroke)
+}
+
+/// Builds a box covering the bounds of the glyph, or its outline if there is a `stroke`, to stand
+/// in for a glyph that could not be tessellated.
+pub fn build_bounds_mesh(
+    font: &impl Font,
+    glyph_id: GlyphId,
+    settings: &GlyphMeshSettings,
+    stroke: Option<&Stroke>,
+) -> Result<GlyphMesh, MeshError> {
+    let path = build_bounds_path(font, glyph_id)?;
+    match stroke {
+        Some(stroke) => outline_mesh(path, settings, stroke),
+        None => fill_mesh(path, settings),
+    }
+}
+
+/// Combines glyph meshes, each moved into place by its transform, into a single mesh.
+pub(crate) fn merge_meshes<'a>(glyphs: impl IntoIterator<Item = (&'a Mesh, Transform)>) -> Mesh {
+    let mut buffers = MeshBuffers::default();
+    for (mesh, transform) in glyphs {
+        buffers.push_mesh(mesh, &transform);
+    }
+    buffers.into_mesh()
+}
+
+fn fill_mesh(glyph_path: GlyphPath, settings: &GlyphMeshSettings) -> Result<GlyphMesh, MeshError> {
+    let GlyphPath {
+        path,
+        filler,
+        font_scale,
+    } = glyph_path;
+    let tolerance = settings.tolerance * font_scale;
+    let options = FillOptions::tolerance(tolerance).with_fill_rule(settings.fill_rule);
+    let mut buffers = MeshBuffers::default();
+
+    let depth = settings.depth * font_scale;
+    if depth > 0.0 {
+        let geometry = fill(&path, filler, &options)?;
+        let boundary = boundary(&geometry);
+        // points where contours touch can't be moved in for both, so they stay in place
+        let mut seen = HashSet::new();
+        let pinched = boundary
+            .iter()
+            .flatten()
+            .filter(|&&index| !seen.insert(index))
+            .copied()
+            .collect::<HashSet<_>>();
+        let contours = boundary
+            .iter()
+            .map(|indices| {
+                let mut contour = Contour::new(
+                    indices
+                        .iter()
+                        .map(|&index| {
+                            let [x, y, _] = geometry.vertices[index as usize].position;
+                            lyon::math::point(x, y)
+                        })
+                        .collect(),
+                );
+                for (i, index) in indices.iter().enumerate() {
+                    if pinched.contains(index) {
+                        contour.miters[i] = Vec2::ZERO;
+                    }
+                }
+                contour
+            })
+            .collect::<Vec<_>>();
+
+        // the bevels at the front and the back have to fit within the depth
+        let bevel_width = (settings.bevel.width * font_scale).min(depth / 2.0);
+        let segments = settings.bevel.wall_segments(bevel_width, depth);
+
+        // the faces shrink to make room for the bevel, by moving in the points on their edges, so
+        // they share every point with the bevel rather than being tessellated again
+        let cap_inset = segments[0].front.inset;
+        let mut caps = geometry;
+        if cap_inset > 0.0 {
+            for (indices, contour) in boundary.iter().zip(contours.iter()) {
+                for (i, &index) in indices.iter().enumerate() {
+                    let point = contour.inset_point(i, cap_inset);
+                    caps.vertices[index as usize] = VertexInfo {
+                        position: [point.x, point.y, 0.0],
+                        uv: filler.uv(point.x, point.y),
+                    };
+                }
+            }
+        }
+        buffers.push_face(&caps, 0.0, Vec3::Z);
+        buffers.push_face(&caps, -depth, Vec3::NEG_Z);
+
+        for contour in contours.iter() {
+            for segment in segments.iter() {
+                buffers.push_wall(contour, segment, &filler);
+            }
+        }
+    } else {
+        let geometry = fill(&path, filler, &options)?;
+        buffers.push_face(&geometry, 0.0, Vec3::Z);
+        if settings.back_face == BackFace::Mirrored {
+            buffers.push_face(&geometry, 0.0, Vec3::NEG_Z);
         }
     }
 
-    let path = builder.build();
+    Ok(buffers.into_glyph_mesh(&filler, font_scale))
+}
+
+fn outline_mesh(
+    glyph_path: GlyphPath,
+    settings: &GlyphMeshSettings,
+    stroke: &Stroke,
+) -> Result<GlyphMesh, MeshError> {
+    let GlyphPath {
+        path,
+        filler,
+        font_scale,
+    } = glyph_path;
 
     let mut geometry: VertexBuffers<VertexInfo, u32> = VertexBuffers::new();
+    StrokeTessellator::new()
+        .tessellate_path(
+            &path,
+            &StrokeOptions::tolerance(settings.tolerance * font_scale)
+                .with_line_width(stroke.width * font_scale)
+                .with_line_join(stroke.line_join)
+                .with_line_cap(stroke.line_cap),
+            &mut BuffersBuilder::new(&mut geometry, filler),
+        )
+        .map_err(MeshError::TessellationError)?;
+
+    let mut buffers = MeshBuffers::default();
+    let z = OUTLINE_OFFSET * font_scale;
+    buffers.push_face(&geometry, z, Vec3::Z);
+    if settings.back_face == BackFace::Mirrored {
+        buffers.push_face(&geometry, z, Vec3::NEG_Z);
+    }
 
+    Ok(buffers.into_glyph_mesh(&filler, font_scale))
+}
+
+fn fill(
+    path: &Path,
+    filler: VertexFiller,
+    options: &FillOptions,
+) -> Result<VertexBuffers<VertexInfo, u32>, MeshError> {
+    let mut geometry: VertexBuffers<VertexInfo, u32> = VertexBuffers::new();
     FillTessellator::new()
         .tessellate_path(
-            &path,
-            &FillOptions::default(),
-            &mut BuffersBuilder::new(
-                &mut geometry,
-                VertexFiller {
-                    min_x,
-                    min_y,
-                    width,
-                    height,
-                },
-            ),
+            path,
+            options,
+            &mut BuffersBuilder::new(&mut geometry, filler),
         )
         .map_err(MeshError::TessellationError)?;
+    Ok(geometry)
+}
 
-    let normals = vec![Vec3::Z.to_array(); geometry.vertices.len()];
-    let mut positions = Vec::<[f32; 3]>::with_capacity(geometry.vertices.len());
-    let mut uvs = Vec::<[f32; 2]>::with_capacity(geometry.vertices.len());
+/// The edges of a tessellated face that belong to a single triangle, joined into closed polygons
+/// with the filled area to their left, as the indices of their vertices.
+///
+/// Unlike the contours of the glyph, these don't overlap, and share their points with the face.
+fn boundary(geometry: &VertexBuffers<VertexInfo, u32>) -> Vec<Vec<u32>> {
+    let point = |index: u32| {
+        let [x, y, _] = geometry.vertices[index as usize].position;
+        lyon::math::point(x, y)
+    };
+    // all triangles are wound the same way, so the overall area tells us which way that is
+    let area = geometry
+        .indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| point(triangle[i]));
+            (b - a).cross(c - a)
+        })
+        .sum::<f32>();
+    let mut edges = HashSet::<(u32, u32)>::new();
+    for triangle in geometry.indices.chunks_exact(3) {
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            let edge = if area > 0.0 {
+                (triangle[from], triangle[to])
+            } else {
+                (triangle[to], triangle[from])
+            };
+            edges.insert(edge);
+        }
+    }
+    // an edge shared by two triangles appears once in each direction
+    let mut next = HashMap::<u32, Vec<u32>>::new();
+    for &(from, to) in edges.iter() {
+        if !edges.contains(&(to, from)) {
+            next.entry(from).or_default().push(to);
+        }
+    }
 
-    for VertexInfo { position, uv } in geometry.vertices.iter() {
-        positions.push(*position);
-        uvs.push(*uv);
+    let mut starts = next.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+    let mut contours = Vec::new();
+    for start in starts {
+        while let Some(mut to) = next.get_mut(&start).and_then(Vec::pop) {
+            let mut contour = vec![start];
+            while to != start {
+                contour.push(to);
+                let Some(after) = next.get_mut(&to).and_then(Vec::pop) else {
+                    break;
+                };
+                to = after;
+            }
+            if contour.len() >= 3 {
+                contours.push(contour);
+            }
+        }
+    }
+    contours
+}
+
+/// A closed polygon with the filled area to its left, and the normals of its edges pointing away
+/// from it.
+struct Contour {
+    points: Vec<lyon::math::Point>,
+    edge_normals: Vec<Vec2>,
+    /// How far each point moves when its edges are offset by one unit.
+    miters: Vec<Vec2>,
+}
+
+impl Contour {
+    fn new(points: Vec<lyon::math::Point>) -> Self {
+        let len = points.len();
+        let edge_normals = (0..len)
+            .map(|i| {
+                let edge = points[(i + 1) % len] - points[i];
+                Vec2::new(edge.y, -edge.x).normalize_or_zero()
+            })
+            .collect::<Vec<_>>();
+        let miters = (0..len)
+            .map(|i| {
+                let previous = edge_normals[(i + len - 1) % len];
+                let current = edge_normals[i];
+                let cos = previous.dot(current);
+                if cos > MIN_MITER_COS {
+                    (previous + current) / (1.0 + cos)
+                } else {
+                    current
+                }
+            })
+            .collect();
+        Self {
+            points,
+            edge_normals,
+            miters,
+        }
     }
 
-    geometry.indices.reverse(); // bevy has a right-handed coordinate system
+    fn len(&self) -> usize {
+        self.points.len()
+    }
 
-    let indices = Indices::U32(geometry.indices);
+    /// The point at index `i`, moved towards the filled area by `inset`.
+    fn inset_point(&self, i: usize, inset: f32) -> lyon::math::Point {
+        let i = i % self.len();
+        let miter = self.miters[i] * inset;
+        self.points[i] - lyon::math::vector(miter.x, miter.y)
+    }
 
-    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    mesh.set_indices(Some(indices));
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-    Ok(GlyphMesh {
-        mesh,
-        width,
-        height,
-        font_scale,
-    })
+    /// The normal at the point at index `i`, as seen from edge `edge`.
+    ///
+    /// Points joining edges at a shallow angle share a smoothed normal, other points use the edge normal.
+    fn point_normal(&self, i: usize, edge: usize) -> Vec2 {
+        let len = self.len();
+        let previous = self.edge_normals[(i + len - 1) % len];
+        let current = self.edge_normals[i % len];
+        if previous.dot(current) > SMOOTHING_ANGLE.cos() {
+            (previous + current).normalize_or_zero()
+        } else {
+            self.edge_normals[edge]
+        }
+    }
+}
+
+/// A ring of vertices following a contour, between the front and back faces.
+#[derive(Clone, Copy, Debug)]
+struct Ring {
+    /// How far the ring is moved towards the filled area.
+    inset: f32,
+    z: f32,
+    /// The normal of the surface at the ring, as (outward, z) components.
+    normal: Vec2,
+}
+
+/// A band of quads joining two rings.
+#[derive(Clone, Copy, Debug)]
+struct WallSegment {
+    front: Ring,
+    back: Ring,
+}
+
+#[derive(Default)]
+struct MeshBuffers {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuffers {
+    /// Appends a tessellated face at depth `z`, facing towards `normal`.
+    fn push_face(&mut self, geometry: &VertexBuffers<VertexInfo, u32>, z: f32, normal: Vec3) {
+        let offset = self.positions.len() as u32;
+        for VertexInfo { position, uv } in geometry.vertices.iter() {
+            self.positions.push([position[0], position[1], z]);
+            self.normals.push(normal.to_array());
+            self.uvs.push(*uv);
+        }
+        let indices = geometry.indices.iter().map(|index| index + offset);
+        if normal.z > 0.0 {
+            self.indices.extend(indices.rev()); // bevy has a right-handed coordinate system
+        } else {
+            self.indices.extend(indices);
+        }
+    }
+
+    /// Appends a glyph mesh, moved into place by `transform`.
+    fn push_mesh(&mut self, mesh: &Mesh, transform: &Transform) {
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+            Some(VertexAttributeValues::Float32x2(uvs)),
+            Some(Indices::U32(indices)),
+        ) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+            mesh.indices(),
+        )
+        else {
+            return;
+        };
+        let offset = self.positions.len() as u32;
+        self.positions.extend(
+            positions
+                .iter()
+                .map(|position| transform.transform_point(Vec3::from(*position)).to_array()),
+        );
+        // glyphs are only ever scaled uniformly, so rotating the normals is enough
+        self.normals.extend(
+            normals
+                .iter()
+                .map(|normal| (transform.rotation * Vec3::from(*normal)).to_array()),
+        );
+        self.uvs.extend(uvs.iter().copied());
+        self.indices
+            .extend(indices.iter().map(|index| index + offset));
+    }
+
+    fn into_mesh(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh
+    }
+
+    fn into_glyph_mesh(self, filler: &VertexFiller, font_scale: f32) -> GlyphMesh {
+        GlyphMesh {
+            mesh: self.into_mesh(),
+            width: filler.width,
+            height: filler.height,
+            font_scale,
+        }
+    }
+
+    /// Joins two rings along a contour with quads facing away from the filled area.
+    fn push_wall(&mut self, contour: &Contour, segment: &WallSegment, filler: &VertexFiller) {
+        for edge in 0..contour.len() {
+            let offset = self.positions.len() as u32;
+            for i in [edge, edge + 1] {
+                let point_normal = contour.point_normal(i, edge);
+                for ring in [segment.front, segment.back] {
+                    let point = contour.inset_point(i, ring.inset);
+                    let normal = (point_normal * ring.normal.x).extend(ring.normal.y);
+                    self.positions.push([point.x, point.y, ring.z]);
+                    self.normals.push(normal.normalize_or_zero().to_array());
+                    self.uvs.push(filler.uv(point.x, point.y));
+                }
+            }
+            // vertices are [from front, from back, to front, to back]
+            let [from_front, from_back, to_front, to_back] = [0, 1, 2, 3].map(|i| offset + i);
+            self.indices.extend([
+                from_front, from_back, to_front, to_front, from_back, to_back,
+            ]);
+        }
+    }
 }
 
 trait ToLyonPoint {
@@ -133,6 +871,7 @@ enum CurveMapping {
     Cubic(lyon::math::Point, lyon::math::Point),
 }
 
+#[derive(Clone, Copy)]
 struct VertexFiller {
     min_x: f32,
     min_y: f32,
@@ -140,20 +879,38 @@ struct VertexFiller {
     height: f32,
 }
 
+impl VertexFiller {
+    fn uv(&self, x: f32, y: f32) -> [f32; 2] {
+        [
+            (x - self.min_x) / self.width,
+            1.0 - (y - self.min_y) / self.height,
+        ]
+    }
+}
+
 impl FillVertexConstructor<VertexInfo> for VertexFiller {
     fn new_vertex(&mut self, vertex: FillVertex) -> VertexInfo {
         let position = vertex.position();
         let Point2D { x, y, .. } = position;
         VertexInfo {
             position: [x, y, 0.0],
-            uv: [
-                (x - self.min_x) / self.width,
-                1.0 - (y - self.min_y) / self.height,
-            ],
+            uv: self.uv(x, y),
         }
     }
 }
 
+impl StrokeVertexConstructor<VertexInfo> for VertexFiller {
+    fn new_vertex(&mut self, vertex: StrokeVertex) -> VertexInfo {
+        let position = vertex.position();
+        let Point2D { x, y, .. } = position;
+        VertexInfo {
+            position: [x, y, 0.0],
+            uv: self.uv(x, y),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct GlyphMesh {
     pub mesh: Mesh,
     pub width: f32,
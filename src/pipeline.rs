@@ -1,13 +1,25 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
-    mesh::{self, MeshError},
+    layout::{LaidOutText, TextLayouter},
+    mesh::{self, BackFace, GlyphMeshSettings, MeshError, Stroke},
     plugin::Wireframeable,
 };
 
-use bevy::prelude::*;
-use glyph_brush_layout::ab_glyph::{Font as _, GlyphId, PxScale};
-use glyph_brush_layout::{FontId, GlyphPositioner, Layout, SectionGeometry, SectionText};
+use bevy::{
+    ecs::{query::WorldQuery, system::EntityCommands},
+    prelude::*,
+    sprite::Anchor,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use glyph_brush_layout::ab_glyph::{Font as _, FontArc, GlyphId};
+use glyph_brush_layout::SectionGlyph;
+use lyon::tessellation::TessellationError;
+
+pub use cosmic_text::{FeatureTag, FontFeatures};
 
 #[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
 pub struct Text3d(pub Text);
@@ -24,135 +36,1111 @@ impl From<Text> for Text3d {
     }
 }
 
+/// The measured size of a [`Text3d`], in world units, updated whenever its text is laid out.
+#[derive(Component, Clone, Debug, Default, Deref)]
+pub struct Text3dSize {
+    /// The width and height of the text.
+    #[deref]
+    pub size: Vec2,
+    /// The distance from the top of the text to the first baseline.
+    pub ascent: f32,
+    /// The distance from the last baseline to the bottom of the text.
+    pub descent: f32,
+    /// The lines of the text, from top to bottom.
+    pub lines: Vec<Text3dLine>,
+}
+
+/// The metrics of a line of a [`Text3d`], in world units.
+///
+/// Positions are measured right and down from the top left corner of the text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Text3dLine {
+    /// Where the line starts.
+    pub x: f32,
+    /// The distance from the start to the end of the line.
+    pub width: f32,
+    /// Where the baseline of the line is.
+    pub baseline: f32,
+    /// The distance from the baseline to the top of the tallest font on the line.
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of the deepest font on the line.
+    pub descent: f32,
+}
+
+impl Text3dSize {
+    /// Measures the lines of the laid out glyphs, returning the bounds of the text in layout
+    /// coordinates alongside its size.
+    fn measure<F: glyph_brush_layout::ab_glyph::Font>(
+        glyphs: &[SectionGlyph],
+        fonts: &[F],
+    ) -> (Rect, Self) {
+        let mut lines: Vec<Text3dLine> = Vec::new();
+        for glyph in glyphs.iter() {
+            let font = &fonts[glyph.font_id.0];
+            let scale = glyph.glyph.scale.y / font.height_unscaled();
+            let ascent = font.ascent_unscaled() * scale;
+            let descent = -font.descent_unscaled() * scale;
+            let x = glyph.glyph.position.x;
+            let end = x + font.h_advance_unscaled(glyph.glyph.id) * scale;
+            let baseline = glyph.glyph.position.y;
+            match lines.last_mut() {
+                Some(line) if line.baseline == baseline => {
+                    line.width = line.width.max(end - line.x);
+                    line.ascent = line.ascent.max(ascent);
+                    line.descent = line.descent.max(descent);
+                }
+                _ => lines.push(Text3dLine {
+                    x,
+                    width: end - x,
+                    baseline,
+                    ascent,
+                    descent,
+                }),
+            }
+        }
+
+        let (Some(first), Some(last)) = (lines.first(), lines.last()) else {
+            return (Rect::default(), Self::default());
+        };
+        let bounds = Rect {
+            min: Vec2::new(
+                lines.iter().map(|line| line.x).fold(f32::MAX, f32::min),
+                first.baseline - first.ascent,
+            ),
+            max: Vec2::new(
+                lines
+                    .iter()
+                    .map(|line| line.x + line.width)
+                    .fold(f32::MIN, f32::max),
+                last.baseline + last.descent,
+            ),
+        };
+        let ascent = first.ascent;
+        let descent = last.descent;
+        for line in lines.iter_mut() {
+            line.x -= bounds.min.x;
+            line.baseline -= bounds.min.y;
+        }
+        let size = Self {
+            size: bounds.size(),
+            ascent,
+            descent,
+            lines,
+        };
+        (bounds, size)
+    }
+}
+
+/// Traces the outline of each glyph of a [`Text3d`], with its own mesh and material.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Text3dOutline {
+    pub stroke: Stroke,
+    pub color: Color,
+    /// Whether the glyphs are filled in as well as outlined. If not, the letters are hollow.
+    pub fill: bool,
+}
+
+impl Default for Text3dOutline {
+    fn default() -> Self {
+        Self {
+            stroke: Stroke::default(),
+            color: Color::BLACK,
+            fill: true,
+        }
+    }
+}
+
+/// Picks the tessellation tolerance of a [`Text3d`] from its distance to the nearest camera,
+/// updating its [`GlyphMeshSettings`].
+///
+/// Each level produces its own set of glyph meshes, so keep the number of levels small.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Text3dLod {
+    /// `(distance, tolerance)` pairs, sorted by distance. Each level applies from its distance up
+    /// to the distance of the next level.
+    levels: Vec<(f32, f32)>,
+}
+
+impl Text3dLod {
+    pub fn new(levels: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        let mut levels = levels.into_iter().collect::<Vec<_>>();
+        levels.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { levels }
+    }
+
+    pub fn levels(&self) -> &[(f32, f32)] {
+        &self.levels
+    }
+
+    /// The tolerance for text at `distance` from the camera, if any level applies.
+    pub fn tolerance(&self, distance: f32) -> Option<f32> {
+        self.levels
+            .iter()
+            .take_while(|(level_distance, _)| *level_distance <= distance)
+            .last()
+            .map(|(_, tolerance)| *tolerance)
+    }
+}
+
+/// Limits the size of a [`Text3d`], in world units.
+///
+/// Lines wider than the bounds are wrapped according to the [`BreakLineOn`] of the text, and text
+/// that still doesn't fit is handled according to the [`Text3dOverflow`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Text3dBounds {
+    pub size: Vec2,
+    pub overflow: Text3dOverflow,
+}
+
+impl Text3dBounds {
+    pub const UNBOUNDED: Self = Self {
+        size: Vec2::splat(f32::INFINITY),
+        overflow: Text3dOverflow::Clip,
+    };
+
+    pub fn new(size: Vec2) -> Self {
+        Self { size, ..default() }
+    }
+
+    pub fn with_overflow(mut self, overflow: Text3dOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+impl Default for Text3dBounds {
+    fn default() -> Self {
+        Self::UNBOUNDED
+    }
+}
+
+/// What happens to the part of a [`Text3d`] that doesn't fit within its [`Text3dBounds`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Text3dOverflow {
+    /// Glyphs that don't fit are left out.
+    #[default]
+    Clip,
+    /// Glyphs that don't fit are left out, and the last line that fits ends with an ellipsis.
+    Ellipsis,
+    /// The text is scaled down until it fits.
+    ShrinkToFit,
+}
+
+/// The point of a [`Text3d`] that sits at the origin of its entity, relative to the measured
+/// [`Text3dSize`] of the text. Top left is `(-0.5, 0.5)` and the default, centre, is `(0.0, 0.0)`.
 #[derive(Component, Clone, Copy, Debug, Default, Deref, DerefMut)]
-pub struct Text3dSize(Vec2);
+pub struct Text3dAnchor(pub Anchor);
+
+impl From<Anchor> for Text3dAnchor {
+    fn from(anchor: Anchor) -> Self {
+        Self(anchor)
+    }
+}
+
+/// How the lines of a [`Text3d`] are aligned, overriding the [`TextAlignment`] of the text.
+///
+/// Unlike `TextAlignment`, it can justify lines, stretching the spaces between words so every line
+/// of a paragraph but the last is as wide as the [`Text3dBounds`], or as the widest line if the
+/// text is unbounded.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Text3dAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Justified,
+}
 
-pub(crate) fn queue_text<M: AsMut<Assets<Mesh>>, F: AsRef<Assets<Font>>>(
+impl From<TextAlignment> for Text3dAlignment {
+    fn from(alignment: TextAlignment) -> Self {
+        match alignment {
+            TextAlignment::Left => Self::Left,
+            TextAlignment::Center => Self::Center,
+            TextAlignment::Right => Self::Right,
+        }
+    }
+}
+
+/// The ellipsis that ends clipped text, followed by what stands in for it in fonts without one.
+const ELLIPSIS: char = '\u{2026}';
+const ELLIPSIS_FALLBACK: &str = "...";
+
+/// How many times the text is laid out again to find the largest scale at which it fits.
+const SHRINK_TO_FIT_STEPS: usize = 10;
+
+// `Option::is_none_or` needs Rust 1.82
+#[allow(unknown_lints, clippy::unnecessary_map_or)]
+fn is_whitespace(glyph: &SectionGlyph, text: &Text) -> bool {
+    text.sections[glyph.section_index].value[glyph.byte_index..]
+        .chars()
+        .next()
+        .map_or(true, char::is_whitespace)
+}
+
+/// Whether the glyph lies within the bounds. Whitespace always fits, as it isn't drawn.
+fn fits<F: glyph_brush_layout::ab_glyph::Font>(
+    glyph: &SectionGlyph,
+    text: &Text,
+    fonts: &[F],
+    bounds: &Text3dBounds,
+) -> bool {
+    if is_whitespace(glyph, text) {
+        return true;
+    }
+    let font = &fonts[glyph.font_id.0];
+    let scale = glyph.glyph.scale.y / font.height_unscaled();
+    let start = glyph.glyph.position.x;
+    let end = start + font.h_advance_unscaled(glyph.glyph.id) * scale;
+    let bottom = glyph.glyph.position.y - font.descent_unscaled() * scale;
+    // allow for rounding in the layout
+    let epsilon = 1e-3 * glyph.glyph.scale.y;
+    start >= -epsilon && end <= bounds.size.x + epsilon && bottom <= bounds.size.y + epsilon
+}
+
+/// Lays out the text within its bounds, applying the overflow policy to whatever doesn't fit.
+///
+/// Returns `None` if any font of the text has not loaded yet.
+fn layout_bounded(
+    text: &Text,
+    features: &[FontFeatures],
+    alignment: Text3dAlignment,
+    layouter: &mut TextLayouter,
+    fonts: &Assets<Font>,
+    bounds: &Text3dBounds,
+) -> Option<LaidOutText> {
+    // lines below the bounds are left out by `fits` rather than the layout, so they can be
+    // detected when the text has to shrink or end with an ellipsis
+    let mut laid_out = layouter.layout(text, features, alignment, fonts, bounds.size.x, 1.0)?;
+    let all_fit = |laid_out: &LaidOutText| {
+        laid_out
+            .glyphs
+            .iter()
+            .all(|glyph| fits(glyph, text, &laid_out.fonts, bounds))
+    };
+    if all_fit(&laid_out) {
+        return Some(laid_out);
+    }
+
+    match bounds.overflow {
+        Text3dOverflow::Clip => {
+            let LaidOutText { glyphs, fonts, .. } = &mut laid_out;
+            glyphs.retain(|glyph| fits(glyph, text, fonts, bounds));
+        }
+        Text3dOverflow::Ellipsis => {
+            let glyphs = std::mem::take(&mut laid_out.glyphs);
+            laid_out.glyphs = ellipsize(glyphs, text, &laid_out.fonts, bounds);
+        }
+        Text3dOverflow::ShrinkToFit => {
+            // the largest scale known to fit, and the smallest known not to
+            let (mut fitting, mut overflowing) = (None, 1.0);
+            let mut lower = 0.0;
+            for _ in 0..SHRINK_TO_FIT_STEPS {
+                let scale = (lower + overflowing) / 2.0;
+                let laid_out =
+                    layouter.layout(text, features, alignment, fonts, bounds.size.x, scale)?;
+                if all_fit(&laid_out) {
+                    lower = scale;
+                    fitting = Some(laid_out);
+                } else {
+                    overflowing = scale;
+                }
+            }
+            laid_out = fitting.unwrap_or_default();
+        }
+    }
+    Some(laid_out)
+}
+
+/// Leaves out the glyphs that don't fit, ending the last line that does with an ellipsis.
+fn ellipsize<F: glyph_brush_layout::ab_glyph::Font>(
+    glyphs: Vec<SectionGlyph>,
+    text: &Text,
+    fonts: &[F],
+    bounds: &Text3dBounds,
+) -> Vec<SectionGlyph> {
+    // the glyphs up to the first one that doesn't fit
+    let mut glyphs = glyphs
+        .into_iter()
+        .take_while(|glyph| fits(glyph, text, fonts, bounds))
+        .collect::<Vec<_>>();
+    let Some(last) = glyphs.last().cloned() else {
+        return glyphs;
+    };
+
+    // the ellipsis is set in the font of the last glyph, on its line
+    let font = &fonts[last.font_id.0];
+    let scale = last.glyph.scale.y / font.height_unscaled();
+    let ellipsis = match font.glyph_id(ELLIPSIS) {
+        NOTDEF => vec![font.glyph_id('.'); ELLIPSIS_FALLBACK.len()],
+        id => vec![id],
+    };
+    let ellipsis_width = ellipsis
+        .iter()
+        .map(|id| font.h_advance_unscaled(*id) * scale)
+        .sum::<f32>();
+
+    // make room for the ellipsis
+    let baseline = last.glyph.position.y;
+    let mut x = last.glyph.position.x;
+    while let Some(glyph) = glyphs.last() {
+        if glyph.glyph.position.y != baseline {
+            break;
+        }
+        let glyph_font = &fonts[glyph.font_id.0];
+        let glyph_scale = glyph.glyph.scale.y / glyph_font.height_unscaled();
+        let end =
+            glyph.glyph.position.x + glyph_font.h_advance_unscaled(glyph.glyph.id) * glyph_scale;
+        if !is_whitespace(glyph, text) && end + ellipsis_width <= bounds.size.x {
+            x = end;
+            break;
+        }
+        x = glyph.glyph.position.x;
+        glyphs.pop();
+    }
+
+    for id in ellipsis {
+        glyphs.push(SectionGlyph {
+            glyph: id.with_scale_and_position(last.glyph.scale, (x, baseline)),
+            ..last.clone()
+        });
+        x += font.h_advance_unscaled(id) * scale;
+    }
+    glyphs
+}
+
+/// Fonts to set characters in when the font of their section doesn't have them, tried in order.
+///
+/// Text waits for its fallback fonts to load, like it does for the fonts of its sections. Fallback
+/// fonts that fail to load are left out, with a warning.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Text3dFontFallback {
+    pub fonts: Vec<Handle<Font>>,
+}
+
+/// OpenType features for the sections of a [`Text3d`], such as tabular numbers (`tnum`) or small
+/// caps (`smcp`).
+///
+/// Sections are matched by index. Fonts apply their default features, such as kerning and
+/// standard ligatures, unless they are turned off here.
+#[derive(Component, Clone, Debug, Default, Deref, DerefMut)]
+pub struct Text3dFontFeatures(pub Vec<FontFeatures>);
+
+/// Bakes the glyphs of a [`Text3d`] into one mesh per material, instead of an entity per glyph.
+///
+/// This is much cheaper to draw for long texts, but the glyphs can't be moved individually.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Text3dMerged;
+
+/// Materials for the sections of a [`Text3d`], used instead of the colour of their style.
+///
+/// Sections are matched by index, and sections without a material keep their colour. The
+/// material type is the one the [`Text3dPlugin`](crate::plugin::Text3dPlugin) was added with.
+#[derive(Component, Clone, Debug, Deref, DerefMut)]
+pub struct Text3dMaterials<M: Material = StandardMaterial>(pub Vec<Option<Handle<M>>>);
+
+impl<M: Material> Default for Text3dMaterials<M> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<M: Material> From<Vec<Handle<M>>> for Text3dMaterials<M> {
+    fn from(materials: Vec<Handle<M>>) -> Self {
+        Self(materials.into_iter().map(Some).collect())
+    }
+}
+
+/// Everything that determines the glyph entities of a [`Text3d`].
+#[derive(WorldQuery)]
+pub(crate) struct Text3dQuery<M: Material> {
+    pub(crate) entity: Entity,
+    pub(crate) text: &'static Text3d,
+    pub(crate) settings: Option<&'static GlyphMeshSettings>,
+    pub(crate) outline: Option<&'static Text3dOutline>,
+    pub(crate) bounds: Option<&'static Text3dBounds>,
+    pub(crate) anchor: Option<&'static Text3dAnchor>,
+    pub(crate) materials: Option<&'static Text3dMaterials<M>>,
+    pub(crate) merged: Has<Text3dMerged>,
+    pub(crate) features: Option<&'static Text3dFontFeatures>,
+    pub(crate) alignment: Option<&'static Text3dAlignment>,
+    pub(crate) glyph_entities: Option<&'static GlyphEntities<M>>,
+}
+
+/// The material of a glyph entity: a colour, or a material given for its section.
+enum GlyphMaterial<M: Material> {
+    Color(Handle<StandardMaterial>),
+    Custom(Handle<M>),
+}
+
+impl<M: Material> Clone for GlyphMaterial<M> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Color(handle) => Self::Color(handle.clone()),
+            Self::Custom(handle) => Self::Custom(handle.clone()),
+        }
+    }
+}
+
+impl<M: Material> PartialEq for GlyphMaterial<M> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Color(a), Self::Color(b)) => a == b,
+            (Self::Custom(a), Self::Custom(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<M: Material> Eq for GlyphMaterial<M> {}
+
+impl<M: Material> std::hash::Hash for GlyphMaterial<M> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Color(handle) => handle.hash(state),
+            Self::Custom(handle) => handle.hash(state),
+        }
+    }
+}
+
+impl<M: Material> GlyphMaterial<M> {
+    fn spawn(self, commands: &mut Commands, mesh: Handle<Mesh>, transform: Transform) -> Entity {
+        match self {
+            Self::Color(material) => commands.spawn((
+                Wireframeable,
+                Text3dGenerated,
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform,
+                    ..default()
+                },
+            )),
+            Self::Custom(material) => commands.spawn((
+                Wireframeable,
+                Text3dGenerated,
+                MaterialMeshBundle {
+                    mesh,
+                    material,
+                    transform,
+                    ..default()
+                },
+            )),
+        }
+        .id()
+    }
+
+    /// Swaps the material of a glyph entity for this one.
+    fn replace(self, commands: &mut EntityCommands, old: &Self) {
+        match old {
+            Self::Color(_) => commands.remove::<Handle<StandardMaterial>>(),
+            Self::Custom(_) => commands.remove::<Handle<M>>(),
+        };
+        match self {
+            Self::Color(material) => commands.insert(material),
+            Self::Custom(material) => commands.insert(material),
+        };
+    }
+}
+
+/// Marks the entities spawned to draw a [`Text3d`], which are children of its entity.
+///
+/// They are despawned along with the text when the [`Text3d`] is removed, while other children of
+/// the entity are left alone.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Text3dGenerated;
+
+/// Where a glyph entity of a [`Text3d`] comes from in the text, and where it was laid out.
+///
+/// Each glyph entity has one, and so does its outline and the back of a glyph readable from behind.
+/// Text merged with [`Text3dMerged`] has no glyph entities, so no `Text3dGlyph`s.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Text3dGlyph {
+    /// The section of the text the glyph is in.
+    pub section_index: usize,
+    /// The byte in the section string the glyph starts at.
+    pub byte_index: usize,
+    /// The character in the section string the glyph starts at.
+    pub char_index: usize,
+    /// The character the glyph starts at. A ligature starts at the first of its characters.
+    pub char: char,
+    /// The glyph in its font.
+    pub glyph_id: GlyphId,
+    /// The line the glyph is on, counting from 0 at the top, as in [`Text3dSize::lines`].
+    pub line: usize,
+    /// The box the glyph was laid out in, from its advance and the ascent and descent of its font,
+    /// in the space of the text entity.
+    pub rect: Rect,
+    /// The transform the glyph entity was laid out with, for animating it relative to.
+    pub transform: Transform,
+}
+
+/// The glyph entities spawned for a [`Text3d`], as they were last spawned or updated.
+#[derive(Component)]
+pub(crate) struct GlyphEntities<M: Material>(Vec<GlyphEntity<M>>);
+
+struct GlyphEntity<M: Material> {
+    entity: Entity,
+    mesh: AssetId<Mesh>,
+    transform: Transform,
+    material: GlyphMaterial<M>,
+    glyph: Option<Text3dGlyph>,
+}
+
+/// What a glyph entity draws, and the glyph it draws if it draws only one.
+type GlyphEntityParts<M> = (
+    Handle<Mesh>,
+    Transform,
+    GlyphMaterial<M>,
+    Option<Text3dGlyph>,
+);
+
+/// Updates the glyph entities of a text to draw `glyphs`, keeping as many of the entities as it
+/// can.
+///
+/// Entities drawing the same mesh are kept first, then the rest are given new meshes, transforms
+/// or materials as needed. Only the glyphs beyond those are spawned, and only the entities left
+/// over are despawned.
+fn update_glyph_entities<M: Material>(
+    commands: &mut Commands,
     entity: Entity,
-    text_3d: &Text3d,
+    old: Option<&GlyphEntities<M>>,
+    glyphs: Vec<GlyphEntityParts<M>>,
+) {
+    let old = old.map_or(&[][..], |old| &old.0[..]);
+    let mut by_mesh: HashMap<AssetId<Mesh>, Vec<usize>> = HashMap::new();
+    for (index, glyph_entity) in old.iter().enumerate().rev() {
+        // glyph entities may have been despawned from outside
+        if commands.get_entity(glyph_entity.entity).is_some() {
+            by_mesh.entry(glyph_entity.mesh).or_default().push(index);
+        }
+    }
+    let mut matches = glyphs
+        .iter()
+        .map(|(mesh, ..)| by_mesh.get_mut(&mesh.id()).and_then(Vec::pop))
+        .collect::<Vec<_>>();
+    let mut unmatched = by_mesh.into_values().flatten().collect::<Vec<_>>();
+    unmatched.sort_unstable_by(|a, b| b.cmp(a));
+    for index in matches.iter_mut().filter(|index| index.is_none()) {
+        *index = unmatched.pop();
+    }
+    for index in unmatched {
+        commands.entity(old[index].entity).despawn_recursive();
+    }
+
+    let mut spawned = Vec::new();
+    let glyph_entities = glyphs
+        .into_iter()
+        .zip(matches)
+        .map(|((mesh, transform, material, glyph), index)| {
+            let glyph_entity = GlyphEntity {
+                entity: Entity::PLACEHOLDER,
+                mesh: mesh.id(),
+                transform,
+                material: material.clone(),
+                glyph,
+            };
+            let Some(old) = index.map(|index| &old[index]) else {
+                let entity = material.spawn(commands, mesh, transform);
+                if let Some(glyph) = glyph {
+                    commands.entity(entity).insert(glyph);
+                }
+                spawned.push(entity);
+                return GlyphEntity {
+                    entity,
+                    ..glyph_entity
+                };
+            };
+            let mut entity_commands = commands.entity(old.entity);
+            if old.mesh != mesh.id() {
+                entity_commands.insert(mesh);
+            }
+            if old.transform != transform {
+                entity_commands.insert(transform);
+            }
+            if old.material != material {
+                material.replace(&mut entity_commands, &old.material);
+            }
+            if old.glyph != glyph {
+                match glyph {
+                    Some(glyph) => entity_commands.insert(glyph),
+                    None => entity_commands.remove::<Text3dGlyph>(),
+                };
+            }
+            GlyphEntity {
+                entity: old.entity,
+                ..glyph_entity
+            }
+        })
+        .collect();
+    commands
+        .entity(entity)
+        .push_children(&spawned)
+        .insert(GlyphEntities(glyph_entities));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn queue_text<M: Material, A: AsMut<Assets<Mesh>>, F: AsRef<Assets<Font>>>(
+    text_3d: &Text3dQueryItem<M>,
     commands: &mut Commands,
     font_char_mesh_map: &mut FontGlyphMeshMap,
     waiting: &mut Vec<Entity>,
+    tessellating: &mut Vec<Entity>,
     fonts: &F,
+    layouter: &mut TextLayouter,
+    material_cache: &mut GlyphMaterialCache,
     materials: &mut Assets<StandardMaterial>,
-    meshes: &mut M,
+    meshes: &mut A,
 ) {
-    let (maybe_font_arcs, (sections, styles)): (Vec<Option<_>>, (Vec<_>, Vec<_>)) = text_3d
-        .0
-        .sections
-        .iter()
-        .enumerate()
-        .map(|(idx, section)| {
-            (
-                fonts
-                    .as_ref()
-                    .get(&section.style.font)
-                    .map(|f| f.font.clone()),
-                (
-                    SectionText {
-                        text: &section.value,
-                        scale: PxScale::from(section.style.font_size),
-                        font_id: FontId(idx),
-                    },
-                    section.style.clone(),
-                ),
-            )
-        })
-        .unzip();
-    let Some(font_arcs) = maybe_font_arcs.into_iter().collect::<Option<Vec<_>>>() else {
+    let entity = text_3d.entity;
+    let settings = &text_3d.settings.copied().unwrap_or_default();
+    let bounds = text_3d.bounds.copied().unwrap_or_default();
+    let Some(LaidOutText {
+        glyphs,
+        fonts: font_arcs,
+        handles: font_handles,
+    }) = layout_bounded(
+        text_3d.text,
+        text_3d.features.map_or(&[], |features| &features.0),
+        text_3d
+            .alignment
+            .copied()
+            .unwrap_or(text_3d.text.alignment.into()),
+        layouter,
+        fonts.as_ref(),
+        &bounds,
+    )
+    else {
         waiting.push(entity);
         return;
     };
 
-    let glyphs =
-        Layout::default().calculate_glyphs(&font_arcs, &SectionGeometry::default(), &sections);
+    let (text_bounds, text_size) = Text3dSize::measure(&glyphs, &font_arcs);
 
     let mut children = Vec::with_capacity(glyphs.len());
-    let mut text_bounds = Rect {
-        min: Vec2::splat(std::f32::MAX),
-        max: Vec2::splat(std::f32::MIN),
-    };
+    // whether any glyph mesh is still being tessellated in the background
+    let mut incomplete = false;
+    // lines are told apart by their baselines, as in `Text3dSize::measure`
+    let mut line = None;
+    let mut line_baseline = None;
+    // the section, byte and character of the glyph before, to count characters on from
+    let mut cursor = (usize::MAX, 0, 0);
     for glyph in glyphs.iter() {
-        let style = &styles[glyph.section_index];
+        let section = &text_3d.text.sections[glyph.section_index];
+        let style = &section.style;
+
+        let baseline = glyph.glyph.position.y;
+        if line_baseline != Some(baseline) {
+            line_baseline = Some(baseline);
+            line = Some(line.map_or(0, |line| line + 1));
+        }
+        let font = &font_arcs[glyph.font_id.0];
+        let scale = glyph.glyph.scale.y / font.height_unscaled();
+        let x = glyph.glyph.position.x;
+        let glyph_info = (!text_3d.merged).then(|| {
+            if cursor.0 != glyph.section_index {
+                cursor = (glyph.section_index, 0, 0);
+            }
+            let (_, byte_index, char_index) = &mut cursor;
+            // right-to-left glyphs are laid out from the end of their run
+            if glyph.byte_index >= *byte_index {
+                *char_index += section.value[*byte_index..glyph.byte_index].chars().count();
+            } else {
+                *char_index -= section.value[glyph.byte_index..*byte_index].chars().count();
+            }
+            *byte_index = glyph.byte_index;
+            Text3dGlyph {
+                section_index: glyph.section_index,
+                byte_index: glyph.byte_index,
+                char_index: *char_index,
+                char: section.value[glyph.byte_index..]
+                    .chars()
+                    .next()
+                    .unwrap_or_default(),
+                glyph_id: glyph.glyph.id,
+                line: line.unwrap_or_default(),
+                // in layout coordinates until the text is anchored
+                rect: Rect::new(
+                    x,
+                    baseline - font.descent_unscaled() * scale,
+                    x + font.h_advance_unscaled(glyph.glyph.id) * scale,
+                    baseline - font.ascent_unscaled() * scale,
+                ),
+                transform: Transform::IDENTITY,
+            }
+        });
 
-        let (mesh_data, font_meta) =
-            match font_char_mesh_map.get(meshes, fonts, style.font.clone(), glyph.glyph.id) {
+        // the filled glyph, then its outline
+        let fill = !matches!(text_3d.outline, Some(Text3dOutline { fill: false, .. }));
+        let section_material = text_3d
+            .materials
+            .and_then(|materials| materials.get(glyph.section_index).cloned().flatten());
+        let parts = [
+            fill.then(|| {
+                let material = match &section_material {
+                    Some(material) => GlyphMaterial::Custom(material.clone()),
+                    None => GlyphMaterial::Color(material_cache.get(materials, style.color)),
+                };
+                (None, material)
+            }),
+            text_3d.outline.map(|outline| {
+                let material = GlyphMaterial::Color(material_cache.get(materials, outline.color));
+                (Some(&outline.stroke), material)
+            }),
+        ];
+        for (stroke, material) in parts.into_iter().flatten() {
+            let (mesh_data, font_meta) = match font_char_mesh_map.get(
+                meshes,
+                fonts,
+                font_handles[glyph.font_id.0].clone(),
+                glyph.glyph.id,
+                settings,
+                stroke,
+            ) {
                 Ok((mesh, font_scale)) => (mesh, font_scale),
                 Err(GlyphMeshCreationError::NoOutline) => {
                     continue;
                 }
+                Err(GlyphMeshCreationError::Pending) => {
+                    incomplete = true;
+                    continue;
+                }
                 Err(GlyphMeshCreationError::FontNotYetLoaded) => {
                     waiting.push(entity);
                     return;
                 }
             };
 
-        let font_size = style.font_size;
+            // the font size of the glyph, which is smaller than that of its section if the text
+            // had to shrink to fit
+            let font_size = glyph.glyph.scale.y;
 
-        let scaled_position = glyph.glyph.position;
+            let scaled_position = glyph.glyph.position;
 
-        let scaled_h_advance = mesh_data.unscaled_h_advance * font_size / font_meta.scale;
-        let scaled_descent = font_meta.unscaled_descent * font_size / font_meta.scale;
+            let position = Vec2::new(scaled_position.x, -scaled_position.y);
+            children.push((
+                mesh_data.handle,
+                Transform::from_scale(Vec3::splat(font_size / font_meta.scale))
+                    .with_translation(position.extend(0.0)),
+                material,
+                glyph_info,
+            ));
+        }
+    }
 
-        let x_offset = 0.0;
-        let y_offset = 0.0;
+    if incomplete {
+        // the text is queued again once more of its glyph meshes have been built
+        if !tessellating.contains(&entity) {
+            tessellating.push(entity);
+        }
+        commands.entity(entity).remove::<Text3dReady>();
+        // until then, the glyphs spawned before stay in place
+        if font_char_mesh_map.tessellation == Text3dTessellation::Background {
+            return;
+        }
+    }
 
-        text_bounds = text_bounds.union(Rect {
-            min: Vec2::new(scaled_position.x, 0.),
-            max: Vec2::new(
-                scaled_position.x + scaled_h_advance,
-                scaled_position.y - scaled_descent,
-            ),
-        });
+    // the anchor point of the text, in layout coordinates, goes to the origin
+    let anchor = text_3d.anchor.copied().unwrap_or_default().as_vec();
+    let anchor_point =
+        text_bounds.min + Vec2::new(anchor.x + 0.5, 0.5 - anchor.y) * text_bounds.size();
+    let offset = Vec2::new(anchor_point.x, -anchor_point.y).extend(0.0);
+    let center_x = text_bounds.center().x - anchor_point.x;
 
-        let position = Vec2::new(scaled_position.x + x_offset, -scaled_position.y + y_offset);
-        children.push((
-            Wireframeable,
-            PbrBundle {
-                mesh: mesh_data.handle,
-                // TODO: this should be configurable
-                material: materials.add(style.color.into()),
-                transform: Transform::from_scale(
-                    Vec2::splat(font_size / font_meta.scale).extend(0.0),
-                )
-                .with_translation(position.extend(0.0)),
-                ..default()
-            },
-        ));
-    }
-
-    let center = text_bounds.center();
-    let offset = Vec2::new(center.x, -center.y).extend(0.0);
+    // flat glyphs readable from behind are turned around and mirrored about the centre of the text
+    let readable_from_behind = settings.back_face == BackFace::Readable && settings.depth <= 0.0;
 
     let children = children
         .into_iter()
-        .map(|(wireframeable, mut pbr_bundle)| {
-            pbr_bundle.transform.translation -= offset;
-            commands.spawn((wireframeable, pbr_bundle)).id()
-        })
-        .collect::<Vec<_>>();
-    commands
-        .entity(entity)
-        .insert(Text3dSize(Vec2::new(1.0, 1.0)))
-        .replace_children(&children);
+        .flat_map(|(mesh, mut transform, material, mut glyph)| {
+            transform.translation -= offset;
+            if let Some(glyph) = &mut glyph {
+                glyph.rect = Rect::from_corners(
+                    Vec2::new(glyph.rect.min.x, -glyph.rect.min.y) - offset.truncate(),
+                    Vec2::new(glyph.rect.max.x, -glyph.rect.max.y) - offset.truncate(),
+                );
+                glyph.transform = transform;
+            }
+            let back = readable_from_behind.then(|| {
+                let mut transform = transform;
+                transform.translation.x = 2.0 * center_x - transform.translation.x;
+                transform.rotation = Quat::from_rotation_y(std::f32::consts::PI);
+                let glyph = glyph.map(|glyph| Text3dGlyph {
+                    rect: Rect::new(
+                        2.0 * center_x - glyph.rect.min.x,
+                        glyph.rect.min.y,
+                        2.0 * center_x - glyph.rect.max.x,
+                        glyph.rect.max.y,
+                    ),
+                    transform,
+                    ..glyph
+                });
+                (mesh.clone(), transform, material.clone(), glyph)
+            });
+            std::iter::once((mesh, transform, material, glyph)).chain(back)
+        });
+    let children = if text_3d.merged {
+        // glyphs sharing a material are baked together, in the order they were laid out
+        let mut groups: Vec<(GlyphMaterial<M>, Vec<_>)> = Vec::new();
+        let mut group_indices = HashMap::new();
+        for (mesh, transform, material, _) in children {
+            let index = *group_indices.entry(material.clone()).or_insert_with(|| {
+                groups.push((material, Vec::new()));
+                groups.len() - 1
+            });
+            groups[index].1.push((mesh, transform));
+        }
+        groups
+            .into_iter()
+            .map(|(material, glyphs)| {
+                let glyph_meshes = meshes.as_mut();
+                let mesh = mesh::merge_meshes(glyphs.iter().filter_map(|(mesh, transform)| {
+                    glyph_meshes.get(mesh).map(|mesh| (mesh, *transform))
+                }));
+                (glyph_meshes.add(mesh), Transform::IDENTITY, material, None)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        children.collect::<Vec<_>>()
+    };
+    update_glyph_entities(commands, entity, text_3d.glyph_entities, children);
+    let mut text_entity = commands.entity(entity);
+    text_entity.insert(text_size);
+    if !incomplete {
+        text_entity.insert(Text3dReady);
+    }
 }
 
+/// The colour materials of glyphs, shared by every glyph and [`Text3d`] of the same colour.
 #[derive(Clone, Debug, Default, Resource)]
+pub(crate) struct GlyphMaterialCache {
+    materials: HashMap<ColorMaterialKey, Handle<StandardMaterial>>,
+}
+
+/// The colour a material is made from. Its alpha mode follows from the alpha of the colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct ColorMaterialKey([u32; 4]);
+
+impl From<Color> for ColorMaterialKey {
+    fn from(color: Color) -> Self {
+        Self(color.as_rgba_f32().map(f32::to_bits))
+    }
+}
+
+impl GlyphMaterialCache {
+    /// Retrieve or create the material of a colour
+    fn get(
+        &mut self,
+        materials: &mut Assets<StandardMaterial>,
+        color: Color,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry(color.into())
+            .or_insert_with(|| materials.add(color.into()))
+            .clone()
+    }
+
+    /// Drops the materials no glyph uses any more, so their assets are freed.
+    pub(crate) fn reclaim(&mut self) {
+        self.materials.retain(|_, handle| match handle {
+            Handle::Strong(handle) => Arc::strong_count(handle) > 1,
+            Handle::Weak(_) => false,
+        });
+    }
+}
+
+#[derive(Debug, Default, Resource)]
 pub struct FontGlyphMeshMap {
-    font_to_char_mesh_map: HashMap<Handle<Font>, FontData>,
+    font_to_char_mesh_map: HashMap<AssetId<Font>, FontData>,
+    /// Failures not yet sent as events.
+    failures: Vec<GlyphTessellationFailed>,
+    pub(crate) tessellation: Text3dTessellation,
+    /// Glyph meshes being built in the background.
+    tasks: HashMap<(AssetId<Font>, GlyphMeshKey), Task<GlyphMeshBuild>>,
+    /// Counts the glyph mesh lookups, to tell which meshes were used least recently.
+    uses: u64,
+    /// The glyph meshes cached, over every font.
+    glyph_count: usize,
+    /// The vertices of the glyph meshes cached, over every font.
+    vertex_count: usize,
+    /// Counts the frames, to tell how long glyph meshes have gone unused.
+    frames: u64,
+    /// The frame unused glyph meshes were last looked for on.
+    last_sweep: u64,
+}
+
+/// Limits the glyph meshes [`Text3d`] keeps cached once no glyph uses them.
+///
+/// Glyph meshes no glyph has used for `keep_unused_for` frames, 600 by default, are dropped. On
+/// top of that, while over either of `max_glyphs` and `max_vertices`, the unused glyph meshes used
+/// least recently are dropped, though neither is set by default. Glyph meshes in use, including
+/// those held by a [`Text3dPrewarm`], are never dropped, so the cache can stay over its limits
+/// while the text in the world needs them.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Text3dCacheBudget {
+    /// The frames to keep a glyph mesh for once no glyph uses it, in case text uses it again, or
+    /// `None` to keep it until its font is unloaded. Unused glyph meshes are looked for every
+    /// [`Text3dCacheBudget::SWEEP_INTERVAL`] frames, so they can be kept for up to that many frames
+    /// longer.
+    pub keep_unused_for: Option<u64>,
+    /// The most glyph meshes to keep.
+    pub max_glyphs: Option<usize>,
+    /// The most vertices to keep, over every glyph mesh.
+    pub max_vertices: Option<usize>,
+}
+
+impl Default for Text3dCacheBudget {
+    fn default() -> Self {
+        Self {
+            keep_unused_for: Some(600),
+            max_glyphs: None,
+            max_vertices: None,
+        }
+    }
+}
+
+impl Text3dCacheBudget {
+    /// The frames between looking for glyph meshes that have gone unused.
+    pub const SWEEP_INTERVAL: u64 = 60;
+
+    fn exceeded_by(&self, glyphs: usize, vertices: usize) -> bool {
+        self.max_glyphs.is_some_and(|max| glyphs > max)
+            || self.max_vertices.is_some_and(|max| vertices > max)
+    }
+}
+
+/// A glyph mesh built in the background, as returned by [`build_glyph_mesh`].
+type GlyphMeshBuild = (Option<mesh::GlyphMesh>, Option<TessellationError>);
+
+/// How glyph meshes that haven't been built yet are tessellated.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Text3dTessellation {
+    /// Glyphs are tessellated as their text is laid out, which can stall the frame. Glyphs already
+    /// being tessellated in the background, such as by a [`Text3dPrewarm`], are spawned once they
+    /// are ready, as with `Progressive`.
+    #[default]
+    Immediate,
+    /// Glyphs are tessellated in the background, and the glyphs of a text are spawned once they
+    /// are all ready.
+    Background,
+    /// Glyphs are tessellated in the background, and spawned as they become ready.
+    Progressive,
+}
+
+/// Marks a [`Text3d`] whose glyphs have all been spawned.
+///
+/// It is removed while the text waits for glyph meshes being tessellated in the background.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Text3dReady;
+
+/// Builds the glyph meshes of the characters of a text ahead of time, such as during a loading
+/// screen, so text using them later doesn't wait on them.
+///
+/// The glyph meshes are built in the background, and a [`Text3dPrewarmProgress`] on the same entity
+/// tracks how many are ready. Only the glyphs the font maps the characters to are built, not those
+/// substituted in when shaping, such as ligatures. Prewarmed glyph meshes are kept while the
+/// `Text3dPrewarm` is, even if no text uses them.
+#[derive(Component, Clone, Debug)]
+pub struct Text3dPrewarm {
+    /// The font of the glyphs.
+    pub font: Handle<Font>,
+    /// The characters to build the glyphs of, such as the strings of a localisation table.
+    pub text: String,
+    /// The settings of the text that will use the glyphs.
+    pub settings: GlyphMeshSettings,
+    /// The outline stroke of the text that will use the glyphs, if it is outlined.
+    pub outline: Option<Stroke>,
 }
 
+impl Text3dPrewarm {
+    /// Prewarms the glyphs of the characters of `text` in `font`, with the default settings.
+    pub fn new(font: Handle<Font>, text: impl Into<String>) -> Self {
+        Self {
+            font,
+            text: text.into(),
+            settings: GlyphMeshSettings::default(),
+            outline: None,
+        }
+    }
+
+    /// The printable ASCII characters.
+    pub fn ascii(font: Handle<Font>) -> Self {
+        Self::new(font, (' '..='~').collect::<String>())
+    }
+
+    pub fn with_settings(mut self, settings: GlyphMeshSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_outline(mut self, stroke: Stroke) -> Self {
+        self.outline = Some(stroke);
+        self
+    }
+}
+
+/// The glyph meshes a [`Text3dPrewarm`] has built, held on to so they aren't dropped from the cache.
+#[derive(Component, Default)]
+pub(crate) struct PrewarmedMeshes {
+    pub(crate) _meshes: Vec<Handle<Mesh>>,
+}
+
+/// How many of the glyph meshes of a [`Text3dPrewarm`] are ready.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Text3dPrewarmProgress {
+    pub ready: usize,
+    pub total: usize,
+}
+
+impl Text3dPrewarmProgress {
+    /// The share of the glyph meshes that are ready, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.ready as f32 / self.total as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.ready == self.total
+    }
+}
+
+/// The glyph fonts use for characters they lack, which also stands in for glyphs that fail to
+/// tessellate.
+pub(crate) const NOTDEF: GlyphId = GlyphId(0);
+
+/// Sent when a glyph could not be tessellated, once per glyph of each font.
+///
+/// The glyph is replaced by the font's `.notdef` glyph, or by a box covering its bounds.
+#[derive(Event, Clone, Debug)]
+pub struct GlyphTessellationFailed {
+    /// A weak handle to the font.
+    pub font: Handle<Font>,
+    pub glyph_id: GlyphId,
+    pub error: TessellationError,
+}
+
+/// A glyph mesh is built for each glyph and settings, and for each outline stroke.
+type GlyphMeshKey = (GlyphId, GlyphMeshSettings, Option<Stroke>);
+
 #[derive(Clone, Debug)]
 struct FontData {
     meta: FontMeta,
-    glyph_mesh_map: HashMap<GlyphId, GlyphMeshMeta>,
+    glyph_mesh_map: HashMap<GlyphMeshKey, GlyphMeshMeta>,
+    /// Glyphs without an outline, which have no mesh.
+    empty_glyphs: HashSet<GlyphMeshKey>,
+    failed_glyphs: HashSet<GlyphId>,
 }
 
 impl FontData {
-    fn new(scale: f32, unscaled_descent: f32) -> Self {
+    fn new(scale: f32) -> Self {
         Self {
-            meta: FontMeta::new(scale, unscaled_descent),
+            meta: FontMeta::new(scale),
             glyph_mesh_map: Default::default(),
+            empty_glyphs: Default::default(),
+            failed_glyphs: Default::default(),
         }
     }
 }
@@ -160,15 +1148,11 @@ impl FontData {
 #[derive(Clone, Copy, Debug)]
 struct FontMeta {
     scale: f32,
-    unscaled_descent: f32,
 }
 
 impl FontMeta {
-    fn new(scale: f32, unscaled_descent: f32) -> Self {
-        Self {
-            scale,
-            unscaled_descent,
-        }
+    fn new(scale: f32) -> Self {
+        Self { scale }
     }
 }
 
@@ -176,15 +1160,170 @@ impl FontMeta {
 struct GlyphMeshMeta {
     handle: Handle<Mesh>,
     _unscaled_size: Vec2,
-    unscaled_h_advance: f32,
+    vertices: usize,
+    /// When the mesh was last looked up, as counted by [`FontGlyphMeshMap::uses`].
+    last_used: u64,
+    /// The frame the mesh was first found unused on, if it hasn't been used since.
+    unused_since: Option<u64>,
 }
 
+impl GlyphMeshMeta {
+    /// Whether no glyph entity, or anything else, holds on to the mesh.
+    fn is_unused(&self) -> bool {
+        match &self.handle {
+            Handle::Strong(handle) => Arc::strong_count(handle) == 1,
+            Handle::Weak(_) => true,
+        }
+    }
+}
+
+#[derive(Debug)]
 enum GlyphMeshCreationError {
     FontNotYetLoaded,
     NoOutline,
+    /// The mesh is being built in the background.
+    Pending,
 }
 
 impl FontGlyphMeshMap {
+    pub(crate) fn take_failures(&mut self) -> Vec<GlyphTessellationFailed> {
+        std::mem::take(&mut self.failures)
+    }
+
+    /// Caches the glyph meshes finished in the background, returning whether there were any.
+    pub(crate) fn finish_tasks(&mut self, meshes: &mut Assets<Mesh>) -> bool {
+        let finished = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        for (font, key) in finished.iter() {
+            let task = self.tasks.remove(&(*font, *key)).unwrap();
+            let (mesh, error) = block_on(task);
+            // the mesh is looked up again when the text waiting on it is queued
+            let _ = self.finish(meshes, *font, key, mesh, error);
+        }
+        !finished.is_empty()
+    }
+
+    /// Drops the cached glyph meshes of a font, and stops building any more.
+    pub(crate) fn remove_font(&mut self, font: AssetId<Font>) {
+        if let Some(font_data) = self.font_to_char_mesh_map.remove(&font) {
+            self.glyph_count -= font_data.glyph_mesh_map.len();
+            self.vertex_count -= font_data
+                .glyph_mesh_map
+                .values()
+                .map(|mesh_data| mesh_data.vertices)
+                .sum::<usize>();
+        }
+        // dropping a task cancels it
+        self.tasks.retain(|(task_font, _), _| *task_font != font);
+    }
+
+    /// Drops the glyph meshes no glyph uses, least recently used first, until the cache is within
+    /// its budget.
+    ///
+    /// Called once a frame, it also drops the glyph meshes that have gone unused for long enough.
+    pub(crate) fn evict(&mut self, budget: &Text3dCacheBudget) {
+        self.frames += 1;
+        if let Some(keep_unused_for) = budget.keep_unused_for {
+            if self.frames - self.last_sweep >= Text3dCacheBudget::SWEEP_INTERVAL {
+                self.last_sweep = self.frames;
+                self.release_unused(keep_unused_for);
+            }
+        }
+
+        if !budget.exceeded_by(self.glyph_count, self.vertex_count) {
+            return;
+        }
+        let mut unused = self
+            .font_to_char_mesh_map
+            .iter()
+            .flat_map(|(font, font_data)| {
+                font_data
+                    .glyph_mesh_map
+                    .iter()
+                    .filter(|(_, mesh_data)| mesh_data.is_unused())
+                    .map(|(key, mesh_data)| (mesh_data.last_used, *font, *key))
+            })
+            .collect::<Vec<_>>();
+        unused.sort_unstable_by_key(|(last_used, ..)| *last_used);
+        for (_, font, key) in unused {
+            if !budget.exceeded_by(self.glyph_count, self.vertex_count) {
+                break;
+            }
+            let font_data = self.font_to_char_mesh_map.get_mut(&font).unwrap();
+            let mesh_data = font_data.glyph_mesh_map.remove(&key).unwrap();
+            self.glyph_count -= 1;
+            self.vertex_count -= mesh_data.vertices;
+        }
+    }
+
+    /// Drops the glyph meshes that have been unused for `keep_unused_for` frames.
+    fn release_unused(&mut self, keep_unused_for: u64) {
+        let frames = self.frames;
+        for font_data in self.font_to_char_mesh_map.values_mut() {
+            font_data.glyph_mesh_map.retain(|_, mesh_data| {
+                if !mesh_data.is_unused() {
+                    mesh_data.unused_since = None;
+                    return true;
+                }
+                let unused_since = *mesh_data.unused_since.get_or_insert(frames);
+                let keep = frames - unused_since < keep_unused_for;
+                if !keep {
+                    self.glyph_count -= 1;
+                    self.vertex_count -= mesh_data.vertices;
+                }
+                keep
+            });
+        }
+    }
+
+    /// Builds the glyph meshes of the characters to prewarm in the background, returning how many
+    /// are ready, or `None` if the font has not loaded yet. The meshes that are ready are added to
+    /// `prewarmed`.
+    pub(crate) fn prewarm<M: AsMut<Assets<Mesh>>, F: AsRef<Assets<Font>>>(
+        &mut self,
+        meshes: &mut M,
+        fonts: &F,
+        prewarm: &Text3dPrewarm,
+        prewarmed: &mut Vec<Handle<Mesh>>,
+    ) -> Option<Text3dPrewarmProgress> {
+        let font = &fonts.as_ref().get(&prewarm.font)?.font;
+        let glyph_ids = prewarm
+            .text
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| font.glyph_id(c))
+            .collect::<HashSet<_>>();
+        let strokes = std::iter::once(None).chain(prewarm.outline.as_ref().map(Some));
+        let mut progress = Text3dPrewarmProgress::default();
+        for stroke in strokes {
+            for &g in glyph_ids.iter() {
+                progress.total += 1;
+                match self.get_with(
+                    meshes,
+                    fonts,
+                    prewarm.font.clone(),
+                    g,
+                    &prewarm.settings,
+                    stroke,
+                    Text3dTessellation::Background,
+                ) {
+                    Ok((mesh_data, _)) => {
+                        prewarmed.push(mesh_data.handle);
+                        progress.ready += 1;
+                    }
+                    Err(GlyphMeshCreationError::NoOutline) => progress.ready += 1,
+                    Err(GlyphMeshCreationError::Pending) => {}
+                    Err(GlyphMeshCreationError::FontNotYetLoaded) => return None,
+                }
+            }
+        }
+        Some(progress)
+    }
+
     // Retrieve or create a glyph mesh
     fn get<M: AsMut<Assets<Mesh>>, F: AsRef<Assets<Font>>>(
         &mut self,
@@ -192,6 +1331,32 @@ impl FontGlyphMeshMap {
         fonts: &F,
         font_handle: Handle<Font>,
         g: GlyphId,
+        settings: &GlyphMeshSettings,
+        stroke: Option<&Stroke>,
+    ) -> Result<(GlyphMeshMeta, FontMeta), GlyphMeshCreationError> {
+        let tessellation = self.tessellation;
+        self.get_with(
+            meshes,
+            fonts,
+            font_handle,
+            g,
+            settings,
+            stroke,
+            tessellation,
+        )
+    }
+
+    /// Retrieve or create a glyph mesh, tessellating it as `tessellation` says if it isn't cached.
+    #[allow(clippy::too_many_arguments)]
+    fn get_with<M: AsMut<Assets<Mesh>>, F: AsRef<Assets<Font>>>(
+        &mut self,
+        meshes: &mut M,
+        fonts: &F,
+        font_handle: Handle<Font>,
+        g: GlyphId,
+        settings: &GlyphMeshSettings,
+        stroke: Option<&Stroke>,
+        tessellation: Text3dTessellation,
     ) -> Result<(GlyphMeshMeta, FontMeta), GlyphMeshCreationError> {
         let meshes = meshes.as_mut();
         let fonts = fonts.as_ref();
@@ -200,48 +1365,288 @@ impl FontGlyphMeshMap {
             .ok_or(GlyphMeshCreationError::FontNotYetLoaded)?;
         let font_data = self
             .font_to_char_mesh_map
-            .entry(font_handle.clone())
+            .entry(font_handle.id())
             .or_insert_with(|| {
                 let font_scale = font.font.height_unscaled();
-                let unscaled_descent = font.font.descent_unscaled();
-                FontData::new(font_scale, unscaled_descent)
+                FontData::new(font_scale)
             });
-        let (mesh_data, meta) = match font_data.glyph_mesh_map.get(&g) {
-            // already in the map
-            Some(mesh_data) => (mesh_data.clone(), font_data.meta),
-            // not yet in the map
-            None => {
-                // build the mesh
-                let mesh_data = match mesh::build_mesh(&font.font, g) {
-                    // built the mesh
-                    Ok(mesh) => {
-                        let handle = meshes.add(mesh.mesh);
-                        let unscaled_h_advance = font.font.h_advance_unscaled(g);
-                        font_data.glyph_mesh_map.insert(
-                            g,
-                            GlyphMeshMeta {
-                                handle,
-                                _unscaled_size: Vec2::new(mesh.width, mesh.height),
-                                unscaled_h_advance,
-                            },
-                        );
-                        font_data.glyph_mesh_map.get(&g).unwrap()
-                    }
-                    // could not build the mesh for this glyph
-                    Err(err) => match err {
-                        // there was no outline for this glyph
-                        MeshError::NoOutline => {
-                            return Err(GlyphMeshCreationError::NoOutline);
-                        }
-                        // failed to tessellate this glyph
-                        MeshError::TessellationError(tess_err) => {
-                            todo!("not yet handled: {tess_err}")
-                        }
-                    },
-                };
-                (mesh_data.clone(), font_data.meta)
+        let key = (g, *settings, stroke.copied());
+        self.uses += 1;
+        if let Some(mesh_data) = font_data.glyph_mesh_map.get_mut(&key) {
+            mesh_data.last_used = self.uses;
+            mesh_data.unused_since = None;
+            return Ok((mesh_data.clone(), font_data.meta));
+        }
+        if font_data.empty_glyphs.contains(&key) {
+            return Err(GlyphMeshCreationError::NoOutline);
+        }
+
+        // a glyph already being built in the background, such as to prewarm it, isn't built again
+        if tessellation != Text3dTessellation::Immediate
+            || self.tasks.contains_key(&(font_handle.id(), key))
+        {
+            let font = font.font.clone();
+            self.tasks
+                .entry((font_handle.id(), key))
+                .or_insert_with(|| {
+                    AsyncComputeTaskPool::get().spawn(async move { build_glyph_mesh(&font, key) })
+                });
+            return Err(GlyphMeshCreationError::Pending);
+        }
+
+        let (mesh, error) = build_glyph_mesh(&font.font, key);
+        self.finish(meshes, font_handle.id(), &key, mesh, error)
+    }
+
+    /// Caches a built glyph mesh, or that the glyph has none.
+    fn finish(
+        &mut self,
+        meshes: &mut Assets<Mesh>,
+        font: AssetId<Font>,
+        key: &GlyphMeshKey,
+        mesh: Option<mesh::GlyphMesh>,
+        error: Option<TessellationError>,
+    ) -> Result<(GlyphMeshMeta, FontMeta), GlyphMeshCreationError> {
+        // the font may have been unloaded while the glyph was being tessellated
+        let Some(font_data) = self.font_to_char_mesh_map.get_mut(&font) else {
+            return Err(GlyphMeshCreationError::FontNotYetLoaded);
+        };
+        let g = key.0;
+        if let Some(error) = error {
+            if font_data.failed_glyphs.insert(g) {
+                warn!("failed to tessellate {g:?} of {font:?}: {error}");
+                self.failures.push(GlyphTessellationFailed {
+                    font: Handle::Weak(font),
+                    glyph_id: g,
+                    error,
+                });
             }
+        }
+        let Some(mesh) = mesh else {
+            font_data.empty_glyphs.insert(*key);
+            return Err(GlyphMeshCreationError::NoOutline);
+        };
+        let mesh_data = GlyphMeshMeta {
+            vertices: mesh.mesh.count_vertices(),
+            handle: meshes.add(mesh.mesh),
+            _unscaled_size: Vec2::new(mesh.width, mesh.height),
+            last_used: self.uses,
+            unused_since: None,
+        };
+        match font_data.glyph_mesh_map.insert(*key, mesh_data.clone()) {
+            Some(replaced) => self.vertex_count -= replaced.vertices,
+            None => self.glyph_count += 1,
+        }
+        self.vertex_count += mesh_data.vertices;
+        Ok((mesh_data, font_data.meta))
+    }
+}
+
+/// Builds the mesh of a glyph, or `None` if it has no outline, along with the error if it failed
+/// to tessellate.
+///
+/// Glyphs that fail to tessellate are stood in for by the `.notdef` glyph, or failing that, a box.
+fn build_glyph_mesh(
+    font: &FontArc,
+    (g, settings, stroke): GlyphMeshKey,
+) -> (Option<mesh::GlyphMesh>, Option<TessellationError>) {
+    let build = |glyph_id| match &stroke {
+        Some(stroke) => mesh::build_outline_mesh(font, glyph_id, &settings, stroke),
+        None => mesh::build_mesh(font, glyph_id, &settings),
+    };
+    match build(g) {
+        Ok(mesh) => (Some(mesh), None),
+        Err(MeshError::NoOutline) => (None, None),
+        Err(MeshError::TessellationError(error)) => {
+            let mesh = (g != NOTDEF)
+                .then(|| build(NOTDEF).ok())
+                .flatten()
+                .or_else(|| mesh::build_bounds_mesh(font, g, &settings, stroke.as_ref()).ok());
+            (mesh, Some(error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Meshes(Assets<Mesh>);
+
+    impl AsMut<Assets<Mesh>> for Meshes {
+        fn as_mut(&mut self) -> &mut Assets<Mesh> {
+            &mut self.0
+        }
+    }
+
+    struct Fonts(Assets<Font>);
+
+    impl AsRef<Assets<Font>> for Fonts {
+        fn as_ref(&self) -> &Assets<Font> {
+            &self.0
+        }
+    }
+
+    fn setup() -> (FontGlyphMeshMap, Meshes, Fonts, Handle<Font>) {
+        let mut fonts = Assets::<Font>::default();
+        let font =
+            Font::try_from_bytes(include_bytes!("../assets/fonts/Fira_Sans-Regular.ttf").to_vec())
+                .unwrap();
+        let font = fonts.add(font);
+        (
+            FontGlyphMeshMap::default(),
+            Meshes(Assets::default()),
+            Fonts(fonts),
+            font,
+        )
+    }
+
+    fn get(
+        map: &mut FontGlyphMeshMap,
+        meshes: &mut Meshes,
+        fonts: &Fonts,
+        font: &Handle<Font>,
+        c: char,
+    ) -> GlyphMeshMeta {
+        let g = fonts.0.get(font).unwrap().font.glyph_id(c);
+        let settings = GlyphMeshSettings::default();
+        let (mesh_data, _) = map
+            .get(meshes, fonts, font.clone(), g, &settings, None)
+            .unwrap();
+        mesh_data
+    }
+
+    /// Checks the counters against the glyph meshes actually cached.
+    fn assert_counts(map: &FontGlyphMeshMap) {
+        let cached = map
+            .font_to_char_mesh_map
+            .values()
+            .flat_map(|font_data| font_data.glyph_mesh_map.values());
+        assert_eq!(map.glyph_count, cached.clone().count());
+        assert_eq!(
+            map.vertex_count,
+            cached.map(|mesh_data| mesh_data.vertices).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn remove_font_forgets_its_glyph_meshes() {
+        let (mut map, mut meshes, fonts, font) = setup();
+        let a = get(&mut map, &mut meshes, &fonts, &font, 'a');
+        let b = get(&mut map, &mut meshes, &fonts, &font, 'b');
+        get(&mut map, &mut meshes, &fonts, &font, 'a');
+        assert_eq!(map.glyph_count, 2);
+        assert_eq!(map.vertex_count, a.vertices + b.vertices);
+        assert_counts(&map);
+
+        map.remove_font(font.id());
+        assert_eq!(map.glyph_count, 0);
+        assert_eq!(map.vertex_count, 0);
+    }
+
+    #[test]
+    fn finishing_a_glyph_again_replaces_it() {
+        let (mut map, mut meshes, fonts, font) = setup();
+        get(&mut map, &mut meshes, &fonts, &font, 'a');
+        let font_arc = &fonts.0.get(&font).unwrap().font;
+        let key = (font_arc.glyph_id('a'), GlyphMeshSettings::default(), None);
+        let (mesh, error) = build_glyph_mesh(font_arc, key);
+        map.finish(&mut meshes.0, font.id(), &key, mesh, error)
+            .unwrap();
+        assert_eq!(map.glyph_count, 1);
+        assert_counts(&map);
+    }
+
+    #[test]
+    fn evict_drops_unused_glyph_meshes_least_recently_used_first() {
+        let (mut map, mut meshes, fonts, font) = setup();
+        let budget = Text3dCacheBudget {
+            keep_unused_for: None,
+            max_glyphs: Some(1),
+            max_vertices: None,
+        };
+        // held on to, as by a glyph entity
+        let a = get(&mut map, &mut meshes, &fonts, &font, 'a');
+        get(&mut map, &mut meshes, &fonts, &font, 'b');
+        get(&mut map, &mut meshes, &fonts, &font, 'c');
+        get(&mut map, &mut meshes, &fonts, &font, 'b');
+
+        map.evict(&budget);
+        // the budget can't be kept to while `a` is in use
+        assert_eq!(map.glyph_count, 1);
+        assert_counts(&map);
+        assert_eq!(
+            get(&mut map, &mut meshes, &fonts, &font, 'a').handle,
+            a.handle
+        );
+
+        drop(a);
+        get(&mut map, &mut meshes, &fonts, &font, 'b');
+        map.evict(&budget);
+        assert_eq!(map.glyph_count, 1);
+        assert_counts(&map);
+    }
+
+    #[test]
+    fn prewarmed_glyph_meshes_are_kept_while_held() {
+        let (mut map, mut meshes, fonts, font) = setup();
+        AsyncComputeTaskPool::get_or_init(Default::default);
+        let prewarm = Text3dPrewarm::new(font, "prewarm");
+        let mut prewarmed = Vec::new();
+        while !map
+            .prewarm(&mut meshes, &fonts, &prewarm, &mut prewarmed)
+            .unwrap()
+            .is_done()
+        {
+            prewarmed.clear();
+            map.finish_tasks(&mut meshes.0);
+        }
+        assert_eq!(prewarmed.len(), 6);
+
+        let budget = Text3dCacheBudget {
+            keep_unused_for: Some(Text3dCacheBudget::SWEEP_INTERVAL),
+            ..default()
+        };
+        for _ in 0..Text3dCacheBudget::SWEEP_INTERVAL * 3 {
+            map.evict(&budget);
+        }
+        assert_eq!(map.glyph_count, 6);
+
+        drop(prewarmed);
+        for _ in 0..Text3dCacheBudget::SWEEP_INTERVAL * 2 {
+            map.evict(&budget);
+        }
+        assert_eq!(map.glyph_count, 0);
+        assert_counts(&map);
+    }
+
+    #[test]
+    fn evict_releases_glyph_meshes_unused_for_long_enough() {
+        let (mut map, mut meshes, fonts, font) = setup();
+        let budget = Text3dCacheBudget {
+            keep_unused_for: Some(Text3dCacheBudget::SWEEP_INTERVAL),
+            ..default()
         };
-        Ok((mesh_data, meta))
+        let a = get(&mut map, &mut meshes, &fonts, &font, 'a');
+        get(&mut map, &mut meshes, &fonts, &font, 'b');
+
+        for _ in 0..Text3dCacheBudget::SWEEP_INTERVAL {
+            map.evict(&budget);
+        }
+        // `b` was only just found unused
+        assert_eq!(map.glyph_count, 2);
+
+        for _ in 0..Text3dCacheBudget::SWEEP_INTERVAL {
+            map.evict(&budget);
+        }
+        assert_eq!(map.glyph_count, 1);
+        assert_eq!(map.vertex_count, a.vertices);
+        assert_counts(&map);
+
+        map.evict(&Text3dCacheBudget {
+            keep_unused_for: None,
+            ..default()
+        });
+        assert_eq!(map.glyph_count, 1);
     }
 }
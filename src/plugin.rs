@@ -1,6 +1,20 @@
-use bevy::{pbr::wireframe::Wireframe, prelude::*};
+use std::{collections::HashSet, marker::PhantomData};
 
-use crate::pipeline::{queue_text, FontGlyphMeshMap, Text3d};
+use bevy::{asset::LoadState, ecs::system::SystemParam, pbr::wireframe::Wireframe, prelude::*};
+
+use crate::{
+    animation::Text3dAnimation,
+    layout::TextLayouter,
+    markup::{Text3dMarkup, Text3dStyles},
+    mesh::GlyphMeshSettings,
+    pipeline::{
+        queue_text, FontGlyphMeshMap, GlyphEntities, GlyphMaterialCache, GlyphTessellationFailed,
+        PrewarmedMeshes, Text3d, Text3dAlignment, Text3dAnchor, Text3dBounds, Text3dCacheBudget,
+        Text3dFontFallback, Text3dFontFeatures, Text3dGenerated, Text3dGlyph, Text3dLod,
+        Text3dMaterials, Text3dMerged, Text3dOutline, Text3dPrewarm, Text3dPrewarmProgress,
+        Text3dQuery, Text3dReady, Text3dSize, Text3dTessellation,
+    },
+};
 
 #[derive(Bundle, Debug, Default)]
 pub struct Text3dBundle {
@@ -16,63 +30,490 @@ pub struct Text3dBundle {
     pub global_transform: GlobalTransform,
     /// The text of the entity.
     pub text: Text3d,
+    /// The settings used to build the glyph meshes of the text.
+    pub mesh_settings: GlyphMeshSettings,
+    /// The point of the text that sits at the origin of the entity.
+    pub anchor: Text3dAnchor,
 }
 
-pub struct Text3dPlugin;
+/// Lays out and spawns the glyphs of [`Text3d`] entities.
+///
+/// Sections with a [`Text3dMaterials`] entry of material `M` use it instead of their colour. Only
+/// one `Text3dPlugin` can be added to an app.
+///
+/// Text is laid out again when its font changes, so fonts can be edited while the app runs if
+/// Bevy's `file_watcher` feature is enabled.
+pub struct Text3dPlugin<M: Material = StandardMaterial>(PhantomData<M>);
+
+impl<M: Material> Default for Text3dPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
-impl Plugin for Text3dPlugin {
+impl<M: Material> Plugin for Text3dPlugin<M> {
     fn build(&self, app: &mut App) {
         app.init_resource::<FontGlyphMeshMap>()
-            .add_systems(PreUpdate, queue_text_3d_system)
-            .add_systems(Update, wireframe_system);
+            .init_resource::<GlyphMaterialCache>()
+            .init_resource::<TextLayouter>()
+            .init_resource::<Text3dFontFallback>()
+            .init_resource::<Text3dTessellation>()
+            .init_resource::<Text3dCacheBudget>()
+            .init_resource::<Text3dStyles>()
+            .add_event::<GlyphTessellationFailed>()
+            .add_systems(
+                PreUpdate,
+                (
+                    markup_system,
+                    despawn_removed_text_system::<M>,
+                    font_events_system,
+                    fallback_fonts_system,
+                    queue_text_3d_system::<M>,
+                    prewarm_system,
+                    evict_glyph_meshes_system,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    lod_system,
+                    wireframe_system,
+                    animate_text_system,
+                    reset_animated_text_system,
+                ),
+            );
+    }
+}
+
+type Text3dChanged<M> = Or<(
+    Changed<Text3d>,
+    Changed<GlyphMeshSettings>,
+    Changed<Text3dOutline>,
+    Changed<Text3dBounds>,
+    Changed<Text3dAnchor>,
+    Changed<Text3dMaterials<M>>,
+    Changed<Text3dMerged>,
+    Changed<Text3dFontFeatures>,
+    Changed<Text3dAlignment>,
+)>;
+
+/// The optional components of a [`Text3d`] that change its glyphs when removed.
+#[derive(SystemParam)]
+struct RemovedText3dComponents<'w, 's, M: Material> {
+    settings: RemovedComponents<'w, 's, GlyphMeshSettings>,
+    outlines: RemovedComponents<'w, 's, Text3dOutline>,
+    bounds: RemovedComponents<'w, 's, Text3dBounds>,
+    anchors: RemovedComponents<'w, 's, Text3dAnchor>,
+    materials: RemovedComponents<'w, 's, Text3dMaterials<M>>,
+    merged: RemovedComponents<'w, 's, Text3dMerged>,
+    features: RemovedComponents<'w, 's, Text3dFontFeatures>,
+    alignments: RemovedComponents<'w, 's, Text3dAlignment>,
+}
+
+impl<M: Material> RemovedText3dComponents<'_, '_, M> {
+    fn read(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        self.settings
+            .read()
+            .chain(self.outlines.read())
+            .chain(self.bounds.read())
+            .chain(self.anchors.read())
+            .chain(self.materials.read())
+            .chain(self.merged.read())
+            .chain(self.features.read())
+            .chain(self.alignments.read())
+    }
+}
+
+/// Sets the sections of text written in markup.
+fn markup_system(
+    styles: Res<Text3dStyles>,
+    mut text_3ds: Query<(Entity, Ref<Text3dMarkup>, &mut Text3d)>,
+) {
+    for (entity, markup, mut text_3d) in text_3ds.iter_mut() {
+        if !markup.is_changed() && !styles.is_changed() {
+            continue;
+        }
+        match styles.parse(&markup) {
+            Ok(sections) => text_3d.sections = sections,
+            Err(error) => warn!("invalid markup on {entity:?}: {error}"),
+        }
+    }
+}
+
+/// Despawns the glyphs of text whose [`Text3d`] was removed, or whose entity was despawned without
+/// its children.
+fn despawn_removed_text_system<M: Material>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Text3d>,
+    glyphs: Query<(Entity, &Parent), With<Text3dGenerated>>,
+) {
+    let removed = removed.read().collect::<HashSet<_>>();
+    if removed.is_empty() {
+        return;
+    }
+    for (glyph, parent) in glyphs.iter() {
+        if removed.contains(&parent.get()) {
+            commands.entity(glyph).despawn_recursive();
+        }
+    }
+    for entity in removed {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(GlyphEntities<M>, Text3dSize, Text3dReady)>();
+        }
+    }
+}
+
+/// Forgets fonts that were unloaded or changed, and lays out the text set in changed fonts again.
+fn font_events_system(
+    mut font_events: EventReader<AssetEvent<Font>>,
+    mut font_char_mesh_map: ResMut<FontGlyphMeshMap>,
+    mut layouter: ResMut<TextLayouter>,
+    mut text_3ds: Query<&mut Text3d>,
+) {
+    for event in font_events.read() {
+        match *event {
+            AssetEvent::Removed { id } => {
+                font_char_mesh_map.remove_font(id);
+                layouter.remove_font(id);
+            }
+            AssetEvent::Modified { id } => {
+                font_char_mesh_map.remove_font(id);
+                layouter.remove_font(id);
+                // any text can fall back to a fallback font
+                let fallback = layouter.fallbacks.iter().any(|handle| handle.id() == id);
+                for mut text_3d in text_3ds.iter_mut() {
+                    if fallback
+                        || text_3d
+                            .sections
+                            .iter()
+                            .any(|section| section.style.font.id() == id)
+                    {
+                        text_3d.set_changed();
+                    }
+                }
+            }
+            _ => {}
+        }
     }
 }
 
-fn queue_text_3d_system(
+/// Gives the layouter the fallback fonts that have loaded or are still loading.
+fn fallback_fonts_system(
+    fallback: Res<Text3dFontFallback>,
+    asset_server: Res<AssetServer>,
+    fonts: Res<Assets<Font>>,
+    mut layouter: ResMut<TextLayouter>,
+) {
+    if fallback.is_changed() {
+        layouter.fallbacks = fallback.fonts.clone();
+    }
+    // text waits for every fallback font, so one that won't load would hold up all text
+    layouter.fallbacks.retain(|handle| {
+        if fonts.contains(handle) {
+            return true;
+        }
+        match asset_server.get_load_state(handle) {
+            Some(LoadState::NotLoaded | LoadState::Loading) => true,
+            Some(LoadState::Failed) => {
+                warn!("fallback font {handle:?} failed to load, so it won't be used");
+                false
+            }
+            Some(LoadState::Loaded) | None => {
+                warn!("fallback font {handle:?} isn't loaded, so it won't be used");
+                false
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_text_3d_system<M: Material>(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     fonts: Res<Assets<Font>>,
     mut font_char_mesh_map: ResMut<FontGlyphMeshMap>,
+    mut layouter: ResMut<TextLayouter>,
+    fallback: Res<Text3dFontFallback>,
+    tessellation: Res<Text3dTessellation>,
+    mut material_cache: ResMut<GlyphMaterialCache>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    text_3ds_changed: Query<(Entity, &Text3d), Changed<Text3d>>,
-    text_3ds_all: Query<(Entity, &Text3d)>,
+    text_3ds_changed: Query<Text3dQuery<M>, Text3dChanged<M>>,
+    text_3ds_all: Query<Text3dQuery<M>>,
+    mut removed: RemovedText3dComponents<M>,
+    mut tessellation_failures: EventWriter<GlyphTessellationFailed>,
     mut waiting_last_tick: Local<Vec<Entity>>,
     mut waiting_next_tick: Local<Vec<Entity>>,
+    mut tessellating: Local<Vec<Entity>>,
 ) {
-    for (entity, text_3d) in waiting_last_tick
+    if tessellation.is_changed() {
+        font_char_mesh_map.tessellation = *tessellation;
+    }
+    // text waiting on glyph meshes built in the background is queued again when any are done
+    if font_char_mesh_map.finish_tasks(&mut meshes) {
+        waiting_last_tick.append(&mut tessellating);
+    }
+    // text that lost any of its optional components has to be queued again too
+    waiting_last_tick.extend(removed.read());
+    // as does all text when the fallback fonts change
+    if fallback.is_changed() && !fallback.is_added() {
+        waiting_last_tick.extend(text_3ds_all.iter().map(|text_3d| text_3d.entity));
+    }
+    // text is queued at most once a tick, as its glyph entities are only updated once the
+    // commands are applied
+    let mut queued = HashSet::new();
+    for text_3d in waiting_last_tick
         .drain(..)
+        .filter(|entity| queued.insert(*entity))
         .filter_map(|entity| text_3ds_all.get(entity).ok())
     {
         eprintln!("queueing text for waiting entities");
         queue_text(
-            entity,
-            text_3d,
+            &text_3d,
             &mut commands,
             &mut font_char_mesh_map,
             &mut waiting_next_tick,
+            &mut tessellating,
             &fonts,
+            &mut layouter,
+            &mut material_cache,
             &mut materials,
             &mut meshes,
         );
     }
-    for (entity, text_3d) in text_3ds_changed.iter() {
+    for text_3d in text_3ds_changed
+        .iter()
+        .filter(|text_3d| !queued.contains(&text_3d.entity))
+    {
         eprintln!("queueing text for changed entities");
         queue_text(
-            entity,
-            text_3d,
+            &text_3d,
             &mut commands,
             &mut font_char_mesh_map,
             &mut waiting_next_tick,
+            &mut tessellating,
             &fonts,
+            &mut layouter,
+            &mut material_cache,
             &mut materials,
             &mut meshes,
         );
     }
+    tessellation_failures.send_batch(font_char_mesh_map.take_failures());
+    material_cache.reclaim();
     // TODO: is the below expensive to do every frame, or is checking waiting_next_tick.len() worse?
     std::mem::swap(&mut *waiting_last_tick, &mut *waiting_next_tick);
 }
 
+fn prewarm_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    fonts: Res<Assets<Font>>,
+    mut font_char_mesh_map: ResMut<FontGlyphMeshMap>,
+    prewarms: Query<(Entity, Ref<Text3dPrewarm>, Option<&Text3dPrewarmProgress>)>,
+    mut removed: RemovedComponents<Text3dPrewarm>,
+) {
+    for (entity, prewarm, progress) in prewarms.iter() {
+        if !prewarm.is_changed() && progress.is_some_and(Text3dPrewarmProgress::is_done) {
+            continue;
+        }
+        let mut prewarmed = Vec::new();
+        let Some(new_progress) =
+            font_char_mesh_map.prewarm(&mut meshes, &fonts, &prewarm, &mut prewarmed)
+        else {
+            continue;
+        };
+        let mut entity = commands.entity(entity);
+        entity.insert(PrewarmedMeshes { _meshes: prewarmed });
+        if progress != Some(&new_progress) {
+            entity.insert(new_progress);
+        }
+    }
+    // the prewarmed glyph meshes can be dropped once no text uses them
+    for entity in removed.read() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<PrewarmedMeshes>();
+        }
+    }
+}
+
+fn evict_glyph_meshes_system(
+    mut font_char_mesh_map: ResMut<FontGlyphMeshMap>,
+    budget: Res<Text3dCacheBudget>,
+) {
+    font_char_mesh_map.evict(&budget);
+}
+
+fn lod_system(
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut text_3ds: Query<(&GlobalTransform, &Text3dLod, &mut GlyphMeshSettings)>,
+) {
+    for (transform, lod, mut settings) in text_3ds.iter_mut() {
+        let Some(distance) = cameras
+            .iter()
+            .map(|camera| camera.translation().distance(transform.translation()))
+            .min_by(f32::total_cmp)
+        else {
+            continue;
+        };
+        if let Some(tolerance) = lod.tolerance(distance) {
+            // only touch the settings when the level changes, as that rebuilds the text
+            if settings.tolerance != tolerance {
+                settings.tolerance = tolerance;
+            }
+        }
+    }
+}
+
+/// The material of a glyph entity while colour effects animate it, along with the material it was
+/// laid out with.
 #[derive(Component)]
+struct AnimatedMaterial {
+    laid_out: Handle<StandardMaterial>,
+    own: Handle<StandardMaterial>,
+}
+
+/// The glyph entities of animated text.
+type AnimatedGlyphs<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Text3dGlyph,
+        &'static mut Transform,
+        &'static mut Visibility,
+        Option<&'static Handle<StandardMaterial>>,
+        Option<&'static AnimatedMaterial>,
+    ),
+    With<Text3dGenerated>,
+>;
+
+fn animate_text_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut text_3ds: Query<(&Text3d, &mut Text3dAnimation, &Children)>,
+    mut glyphs: AnimatedGlyphs,
+) {
+    for (text_3d, mut animation, children) in text_3ds.iter_mut() {
+        animation.elapsed += time.delta_seconds() * animation.speed;
+        // the characters before each section
+        let section_starts = text_3d
+            .sections
+            .iter()
+            .scan(0, |start, section| {
+                let section_start = *start;
+                *start += section.value.chars().count();
+                Some(section_start)
+            })
+            .collect::<Vec<_>>();
+
+        let mut glyph_entities = glyphs.iter_many_mut(children);
+        while let Some((entity, glyph, mut transform, mut visibility, material, animated)) =
+            glyph_entities.fetch_next()
+        {
+            // glyphs laid out for sections since removed are left alone until the text, such as
+            // one waiting on a font, is laid out again
+            let Some(section_start) = section_starts.get(glyph.section_index) else {
+                continue;
+            };
+            let index = section_start + glyph.char_index;
+            // the glyph may have been given a new material since it was last animated
+            let animated = animated.filter(|animated| Some(&animated.own) == material);
+            let laid_out = animated.map(|animated| &animated.laid_out).or(material);
+            let laid_out_material = laid_out.and_then(|material| materials.get(material));
+            let color = laid_out_material.map_or(Color::WHITE, |material| material.base_color);
+            let pose = animation.pose(index, color);
+
+            // glyphs scale about their centres
+            let pivot = glyph.rect.center().extend(glyph.transform.translation.z);
+            transform.set_if_neq(Transform {
+                translation: pivot
+                    + (glyph.transform.translation - pivot) * pose.scale
+                    + pose.offset,
+                scale: glyph.transform.scale * pose.scale,
+                ..glyph.transform
+            });
+            visibility.set_if_neq(if pose.visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+
+            let (Some(laid_out), Some(laid_out_material)) = (laid_out, laid_out_material) else {
+                continue;
+            };
+            let color = pose.color.unwrap_or(laid_out_material.base_color);
+            let alpha_mode = if color.a() < 1.0 {
+                AlphaMode::Blend
+            } else {
+                laid_out_material.alpha_mode
+            };
+            // once its colour settles, such as when it has faded in, the glyph shares the material
+            // it was laid out with again
+            if color == laid_out_material.base_color && alpha_mode == laid_out_material.alpha_mode {
+                if let Some(animated) = animated {
+                    commands
+                        .entity(entity)
+                        .remove::<AnimatedMaterial>()
+                        .insert(animated.laid_out.clone());
+                }
+                continue;
+            }
+            let own = animated.is_none().then(|| StandardMaterial {
+                base_color: color,
+                alpha_mode,
+                ..laid_out_material.clone()
+            });
+            match (animated, own) {
+                (Some(animated), _) => {
+                    if let Some(own) = materials.get_mut(&animated.own) {
+                        if own.base_color != color || own.alpha_mode != alpha_mode {
+                            own.base_color = color;
+                            own.alpha_mode = alpha_mode;
+                        }
+                    }
+                }
+                (None, Some(own)) => {
+                    let own = materials.add(own);
+                    let animated = AnimatedMaterial {
+                        laid_out: laid_out.clone(),
+                        own: own.clone(),
+                    };
+                    commands.entity(entity).insert((own, animated));
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// Puts the glyphs of text that is no longer animated back as they were laid out.
+fn reset_animated_text_system(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Text3dAnimation>,
+    text_3ds: Query<&Children>,
+    mut glyphs: AnimatedGlyphs,
+) {
+    for children in text_3ds.iter_many(removed.read()) {
+        let mut glyph_entities = glyphs.iter_many_mut(children);
+        while let Some((entity, glyph, mut transform, mut visibility, material, animated)) =
+            glyph_entities.fetch_next()
+        {
+            transform.set_if_neq(glyph.transform);
+            visibility.set_if_neq(Visibility::Inherited);
+            if let Some(animated) = animated {
+                let mut entity = commands.entity(entity);
+                entity.remove::<AnimatedMaterial>();
+                if Some(&animated.own) == material {
+                    entity.insert(animated.laid_out.clone());
+                }
+            }
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
 pub(crate) struct Wireframeable;
 
 fn wireframe_system(
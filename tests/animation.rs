@@ -0,0 +1,116 @@
+//! Animates text in a headless app.
+
+use bevy::{input::InputPlugin, prelude::*};
+use bevy_text3d::prelude::*;
+
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        Text3dPlugin::<StandardMaterial>::default(),
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<StandardMaterial>()
+    .init_asset::<Font>();
+    app
+}
+
+fn style(font: &Handle<Font>) -> TextStyle {
+    TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: Color::WHITE,
+    }
+}
+
+#[test]
+fn glyphs_of_sections_since_removed_wait_for_the_text_to_be_laid_out_again() {
+    let mut app = app();
+    let font =
+        Font::try_from_bytes(include_bytes!("../assets/fonts/Fira_Sans-Regular.ttf").to_vec())
+            .unwrap();
+    let mut fonts = app.world.resource_mut::<Assets<Font>>();
+    let font = fonts.add(font);
+    // a font that never finishes loading
+    let loading = fonts.get_handle_provider().reserve_handle().typed::<Font>();
+
+    let text = Text::from_sections([
+        TextSection::new("one", style(&font)),
+        TextSection::new("two", style(&font)),
+        TextSection::new("three", style(&font)),
+    ]);
+    let entity = app
+        .world
+        .spawn((
+            Text3dBundle {
+                text: text.into(),
+                ..default()
+            },
+            Text3dAnimation::new([Text3dEffect::WAVE]),
+        ))
+        .id();
+    app.update();
+    assert!(app.world.get::<Text3dReady>(entity).is_some());
+
+    // the glyphs of the old sections stay until the new font loads
+    app.world.get_mut::<Text3d>(entity).unwrap().0 = Text::from_section("one", style(&loading));
+    app.update();
+    app.update();
+    let children = app.world.get::<Children>(entity).unwrap();
+    let sections = children
+        .iter()
+        .filter_map(|&child| app.world.get::<Text3dGlyph>(child))
+        .map(|glyph| glyph.section_index)
+        .max();
+    assert_eq!(sections, Some(2));
+}
+
+#[test]
+fn glyphs_share_their_material_again_once_faded_in() {
+    let mut app = app();
+    let font =
+        Font::try_from_bytes(include_bytes!("../assets/fonts/Fira_Sans-Regular.ttf").to_vec())
+            .unwrap();
+    let font = app.world.resource_mut::<Assets<Font>>().add(font);
+    let entity = app
+        .world
+        .spawn((
+            Text3dBundle {
+                text: Text::from_section("fade", style(&font)).into(),
+                ..default()
+            },
+            Text3dAnimation::new([Text3dEffect::FADE_IN]),
+        ))
+        .id();
+    let materials = |app: &App| {
+        let children = app.world.get::<Children>(entity).unwrap();
+        children
+            .iter()
+            .map(|&child| {
+                app.world
+                    .get::<Handle<StandardMaterial>>(child)
+                    .unwrap()
+                    .id()
+            })
+            .collect::<Vec<_>>()
+    };
+    app.update();
+    let fading = materials(&app);
+    assert_eq!(fading.len(), 4);
+    assert!(fading[1..].iter().all(|material| *material != fading[0]));
+
+    app.world
+        .get_mut::<Text3dAnimation>(entity)
+        .unwrap()
+        .elapsed = 1.0;
+    app.update();
+    app.update();
+    let faded = materials(&app);
+    assert!(faded.iter().all(|material| *material == faded[0]));
+    assert!(!fading.contains(&faded[0]));
+    assert_eq!(app.world.resource::<Assets<StandardMaterial>>().len(), 1);
+}
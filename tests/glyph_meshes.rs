@@ -0,0 +1,114 @@
+//! Tessellates every glyph of every bundled font.
+
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+};
+use bevy_text3d::mesh::{build_mesh, Bevel, BevelProfile, GlyphMeshSettings, MeshError};
+use glyph_brush_layout::ab_glyph::{Font, FontVec, GlyphId};
+
+fn fonts() -> impl Iterator<Item = (String, FontVec)> {
+    let mut paths = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fonts"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ttf"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.into_iter().map(|path| {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let font = FontVec::try_from_vec(std::fs::read(&path).unwrap()).unwrap();
+        (name, font)
+    })
+}
+
+fn positions(mesh: &Mesh) -> &[[f32; 3]] {
+    match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions,
+        _ => panic!("glyph meshes have 3D positions"),
+    }
+}
+
+fn indices(mesh: &Mesh) -> &[u32] {
+    match mesh.indices() {
+        Some(Indices::U32(indices)) => indices,
+        _ => panic!("glyph meshes have u32 indices"),
+    }
+}
+
+/// Counts the edges, by position, that are not matched by an edge running the other way.
+fn open_edges(mesh: &Mesh) -> usize {
+    let positions = positions(mesh);
+    // adding zero turns -0.0 into 0.0, so positions are told apart by value
+    let key = |index: u32| positions[index as usize].map(|x| (x + 0.0).to_bits());
+    let mut edges = HashMap::<_, i32>::new();
+    for triangle in indices(mesh).chunks(3) {
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            let (from, to) = (key(triangle[from]), key(triangle[to]));
+            if from < to {
+                *edges.entry((from, to)).or_default() += 1;
+            } else if from > to {
+                *edges.entry((to, from)).or_default() -= 1;
+            }
+        }
+    }
+    edges.values().filter(|count| **count != 0).count()
+}
+
+/// Builds every glyph with the settings, asserting that each mesh is closed.
+// `usize::is_multiple_of` needs Rust 1.87
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn assert_every_glyph_is_watertight(settings: GlyphMeshSettings) {
+    let mut failures = vec![];
+    for (name, font) in fonts() {
+        for id in 0..font.glyph_count() {
+            let glyph_id = GlyphId(id as u16);
+            let mesh = match build_mesh(&font, glyph_id, &settings) {
+                Ok(glyph_mesh) => glyph_mesh.mesh,
+                // e.g. spaces
+                Err(MeshError::NoOutline) => continue,
+                Err(err) => {
+                    failures.push(format!("{name} {glyph_id:?}: {err:?}"));
+                    continue;
+                }
+            };
+            let vertex_count = positions(&mesh).len();
+            let indices = indices(&mesh);
+            if indices.is_empty() || indices.len() % 3 != 0 {
+                failures.push(format!("{name} {glyph_id:?}: {} indices", indices.len()));
+            } else if indices.iter().any(|index| *index as usize >= vertex_count) {
+                failures.push(format!("{name} {glyph_id:?}: index out of bounds"));
+            } else {
+                let open = open_edges(&mesh);
+                if open > 0 {
+                    failures.push(format!("{name} {glyph_id:?}: {open} open edges"));
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn every_glyph_is_watertight() {
+    assert_every_glyph_is_watertight(GlyphMeshSettings::default().with_depth(0.1));
+}
+
+#[test]
+fn every_round_bevelled_glyph_is_watertight() {
+    assert_every_glyph_is_watertight(
+        GlyphMeshSettings::default()
+            .with_depth(0.1)
+            .with_bevel(Bevel::new(0.02, 4, BevelProfile::Round)),
+    );
+}
+
+#[test]
+fn every_chamfered_glyph_is_watertight() {
+    assert_every_glyph_is_watertight(
+        GlyphMeshSettings::default()
+            .with_depth(0.1)
+            .with_bevel(Bevel::new(0.02, 1, BevelProfile::Chamfer)),
+    );
+}